@@ -60,6 +60,9 @@ async function main() {
     console.log(await catalogProgram.rpc.grant(
         rootData.nonce,
         0, // NetworkAdmin
+        null, // Network-wide
+        new anchor.BN(0), // Valid from
+        new anchor.BN(0), // Expires
         {
             accounts: {
                 program: catalogProgramPK,
@@ -75,6 +78,9 @@ async function main() {
     console.log(await catalogProgram.rpc.grant(
         rootData.nonce,
         1, // CreateCatalog
+        null, // Network-wide
+        new anchor.BN(0), // Valid from
+        new anchor.BN(0), // Expires
         {
             accounts: {
                 program: catalogProgramPK,
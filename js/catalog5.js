@@ -28,6 +28,11 @@ function getHashBN(val) {
     return new anchor.BN(hashData)
 }
 
+async function getRootData() {
+    const rootData = await programAddress([catalogProgramPK.toBuffer()], catalogProgramPK)
+    return new PublicKey(rootData.pubkey)
+}
+
 async function getURLEntry(url, expandMode = 0) {
     var bufExpand = Buffer.alloc(1)
     bufExpand.writeUInt8(expandMode)
@@ -53,6 +58,7 @@ async function findOrCreateURLEntry(url, expand = 0) {
             url,
             {
                 'accounts': {
+                    rootData: await getRootData(),
                     urlEntry: urlEntry,
                     admin: provider.wallet.publicKey,
                     systemProgram: SystemProgram.programId,
@@ -213,9 +219,13 @@ async function createListing(listingData) {
         new anchor.BN(uuidparse(listingId)),
         {
             'accounts': {
+                rootData: await getRootData(),
                 owner: provider.wallet.publicKey,
                 catalog: catalogPK,
                 listing: listingPK,
+                listingUrl: listingUrl,
+                labelUrl: labelUrl,
+                detailUrl: detailUrl,
                 feePayer: provider.wallet.publicKey,
                 feeSource: walletTokenPK,
                 feeAccount: feeAccountPK,
@@ -1,6 +1,7 @@
 const { Buffer } = require('buffer')
 const { PublicKey, SystemProgram } = require('@solana/web3.js')
 const anchor = require('@coral-xyz/anchor')
+const jsSHA = require('jssha')
 
 const { programAddress, importSecretKey, exportSecretKey, jsonFileRead, jsonFileWrite } = require('../../js/atellix-common')
 
@@ -9,6 +10,40 @@ anchor.setProvider(provider)
 const catalogProgram = anchor.workspace.Catalog
 const catalogProgramPK = catalogProgram.programId
 
+function getHashBN(val) {
+    var shaObj = new jsSHA("SHAKE128", "TEXT", { encoding: "UTF8" })
+    var hashData = shaObj.update(val).getHash("UINT8ARRAY", { outputLen: 128})
+    return new anchor.BN(hashData)
+}
+
+async function findOrCreateURLEntry(rootData, url, expandMode = 0) {
+    var bufExpand = Buffer.alloc(1)
+    bufExpand.writeUInt8(expandMode)
+    var shaObj = new jsSHA("SHAKE128", "TEXT", { encoding: "UTF8" })
+    var hashData = shaObj.update(url).getHash("UINT8ARRAY", { outputLen: 128})
+    var urlEntry = await programAddress([bufExpand, Buffer.from(hashData)], catalogProgramPK)
+    var urlEntryPK = new PublicKey(urlEntry.pubkey)
+    let account = await provider.connection.getAccountInfo(urlEntryPK)
+    if (!account) {
+        console.log('Creating URL Entry: ' + urlEntry.pubkey)
+        console.log(await catalogProgram.rpc.createUrl(
+            expandMode,
+            getHashBN(url),
+            url.length,
+            url,
+            {
+                'accounts': {
+                    rootData: rootData,
+                    urlEntry: urlEntryPK,
+                    admin: provider.wallet.publicKey,
+                    systemProgram: SystemProgram.programId,
+                },
+            },
+        ))
+    }
+    return urlEntryPK
+}
+
 async function createCatalog(catalogData) {
     const rootData = await programAddress([catalogProgramPK.toBuffer()], catalogProgramPK)
     const rootAccount = await catalogProgram.account.rootData.fetch(new PublicKey(rootData.pubkey))
@@ -18,14 +53,24 @@ async function createCatalog(catalogData) {
     var catBuf = Buffer.alloc(8)
     catBuf.writeBigUInt64BE(catId)
     const catalog = await programAddress([Buffer.from('catalog', 'utf8'), catBuf], catalogProgramPK)
+    const nameHash = getHashBN(catalogData['name'])
+    const catalogName = await programAddress([Buffer.from('catalog_name', 'utf8'), nameHash.toArrayLike(Buffer, 'be', 16)], catalogProgramPK)
+    const catalogClosed = await programAddress([Buffer.from('catalog_closed', 'utf8'), catBuf], catalogProgramPK)
+    const descriptionUrl = await findOrCreateURLEntry(new PublicKey(rootData.pubkey), 'http://173.234.24.74:9500/api/catalog/' + catalogData['name'])
     const tx = catalogProgram.transaction.createCatalog(
         catalogData['id'],
+        nameHash,
+        catalogData['name'],
+        1, // Schema version
         {
             'accounts': {
                 rootData: new PublicKey(rootData.pubkey),
                 authData: rootAccount.rootAuthority,
                 authUser: catalogData['admin'].publicKey,
                 catalog: new PublicKey(catalog.pubkey),
+                catalogName: new PublicKey(catalogName.pubkey),
+                catalogClosed: new PublicKey(catalogClosed.pubkey),
+                descriptionUrl: descriptionUrl,
                 catalogSigner: catalogData['signer'],
                 catalogManager: catalogData['manager'],
                 feePayer: catalogData['payer'],
@@ -46,6 +91,7 @@ async function main() {
     const catalogs = [
         // 0 - metadata
         {
+            'name': 'metadata',
             'signer': signer.publicKey,
             'manager': manager.publicKey,
             'payer': provider.wallet.publicKey,
@@ -53,6 +99,7 @@ async function main() {
         },
         // 1 - public
         {
+            'name': 'public',
             'signer': signer.publicKey,
             'manager': manager.publicKey,
             'payer': provider.wallet.publicKey,
@@ -60,6 +107,7 @@ async function main() {
         },
         // 2 - commerce
         {
+            'name': 'commerce',
             'signer': signer.publicKey,
             'manager': manager.publicKey,
             'payer': provider.wallet.publicKey,
@@ -67,6 +115,7 @@ async function main() {
         },
         // 3 - event
         {
+            'name': 'event',
             'signer': signer.publicKey,
             'manager': manager.publicKey,
             'payer': provider.wallet.publicKey,
@@ -74,6 +123,7 @@ async function main() {
         },
         // 4 - realestate
         {
+            'name': 'realestate',
             'signer': signer.publicKey,
             'manager': manager.publicKey,
             'payer': provider.wallet.publicKey,
@@ -81,6 +131,7 @@ async function main() {
         },
         // 5 - investment
         {
+            'name': 'investment',
             'signer': signer.publicKey,
             'manager': manager.publicKey,
             'payer': provider.wallet.publicKey,
@@ -88,6 +139,7 @@ async function main() {
         },
         // 6 - employment
         {
+            'name': 'employment',
             'signer': signer.publicKey,
             'manager': manager.publicKey,
             'payer': provider.wallet.publicKey,
@@ -99,6 +151,7 @@ async function main() {
         catalog['id'] = new anchor.BN(i)
         cat = {}
         cat['id'] = catalog['id'].toString()
+        cat['name'] = catalog['name']
         cat['signer'] = signer.publicKey.toString()
         cat['signer_secret'] = exportSecretKey(signer)
         cat['manager'] = manager.publicKey.toString()
@@ -34,6 +34,9 @@ async function main() {
                 authUser: kp.publicKey,
                 catalog: new PublicKey(catData.catalog),
                 listing: new PublicKey(listing),
+                listingUrl: lstData.listingUrl,
+                labelUrl: lstData.labelUrl,
+                detailUrl: lstData.detailUrl,
                 feeRecipient: provider.wallet.publicKey,
                 systemProgram: SystemProgram.programId,
             },
@@ -33,6 +33,9 @@ async function main() {
                 authUser: provider.wallet.publicKey,
                 catalog: new PublicKey(catData.catalog),
                 listing: new PublicKey(listing),
+                listingUrl: lstData.listingUrl,
+                labelUrl: lstData.labelUrl,
+                detailUrl: lstData.detailUrl,
                 feeRecipient: provider.wallet.publicKey,
                 systemProgram: SystemProgram.programId,
             },
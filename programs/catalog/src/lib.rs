@@ -1,7 +1,9 @@
 use crate::program::Catalog;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{ self, Transfer };
+use anchor_lang::Discriminator;
+use anchor_spl::token::{ self, Transfer, Mint, TokenAccount };
 use solana_program::instruction::Instruction;
+use solana_program::program::set_return_data;
 use solana_program::sysvar::instructions::{ID as IX_ID, load_instruction_at_checked};
 use solana_program::ed25519_program::{ID as ED25519_ID};
 use borsh::{ BorshSerialize, BorshDeserialize };
@@ -22,6 +24,25 @@ pub const VERSION_MINOR: u32 = 0;
 pub const VERSION_PATCH: u32 = 0;
 
 pub const MAX_RBAC: u32 = 128;
+pub const RBAC_VERSION: u8 = 1;             // UserRBAC element layout, 0 = original 8 byte UserRBACV0 elements
+pub const MAX_ROLE_PAGE: u32 = 15;  // Keeps list_roles within the 1024 byte return data limit
+pub const MAX_APPROVALS: usize = 10;
+pub const URL_VERSION: u8 = 1;
+pub const URL_VERSION_UNTRACKED: u8 = 0;     // Migrated from the original layout, ref_count unknown until backfilled
+pub const URL_ALLOCATION_TIMEOUT: i64 = 86400; // Seconds before anyone can abandon an unfinalized URL
+pub const MAX_ROLES: usize = 16;
+pub const ROLE_ADMIN_OWNER: u32 = u32::MAX;   // Role can only be managed by the program owner
+
+// Pause flags for RootData (network) and CatalogInstance (catalog)
+pub const PAUSE_CREATE: u8 = 1;     // Catalogs, listings and URLs
+pub const PAUSE_UPDATE: u8 = 2;     // Listing updates and published updates
+pub const PAUSE_FEES: u8 = 4;       // Fee transfers
+pub const PAUSE_ALL: u8 = PAUSE_CREATE | PAUSE_UPDATE | PAUSE_FEES;
+pub const MAX_CATALOG_SIGNERS: usize = 4;
+pub const CATALOG_VERSION: u8 = 1;          // CatalogInstance layout, the original layout (88 bytes) is converted by migrate_catalog
+pub const MAX_CATALOG_NAME: usize = 64;
+pub const MAX_FEE_EXEMPT: usize = 4;
+pub const ROLE_GRANT_SLOT: u32 = u32::MAX;    // Event slot for grants stored as RoleGrant accounts
 
 #[repr(u16)]
 #[derive(PartialEq, Debug, Eq, Copy, Clone)]
@@ -36,6 +57,25 @@ pub enum Role {             // Role-based access control:
     NetworkAdmin,           // 0 - Can manage RBAC for other users
     CreateCatalog,          // 1 - This signer can create catalogs
     RemoveURL,              // 2 - This signer close URL data
+    CatalogAdmin,           // 3 - Can manage roles assigned to it in RootData.role_admin
+    Pauser,                 // 4 - Can pause and unpause the network or a catalog
+}
+
+#[repr(u8)]
+#[derive(PartialEq, Debug, Eq, Copy, Clone, TryFromPrimitive)]
+pub enum RBACMode {         // Storage used for role checks:
+    Slab,                   // 0 - UserRBAC entries in the root authority slab
+    Accounts,               // 1 - One RoleGrant account per grant
+    Migrating,              // 2 - Slab is read-only while grants are copied with migrate_role_account
+}
+
+#[repr(u8)]
+#[derive(PartialEq, Debug, Eq, Copy, Clone, TryFromPrimitive)]
+pub enum ProposalAction {   // NetworkAdmin actions requiring multisig approval:
+    Grant,                  // 0 - Grant a role
+    Revoke,                 // 1 - Revoke a role
+    SetThreshold,           // 2 - Lower the admin threshold
+    SetRoleAdmin,           // 3 - Change the admin role of a role
 }
 
 #[derive(Copy, Clone)]
@@ -43,10 +83,38 @@ pub enum Role {             // Role-based access control:
 pub struct UserRBAC {
     pub role: Role,
     pub free: u32,
+    pub scoped: u8,         // 1 if the role only applies to 'catalog'
+    pub catalog: u64,
+    pub valid_from: i64,    // 0 = valid immediately
+    pub expires: i64,       // 0 = never expires
 }
 unsafe impl Zeroable for UserRBAC {}
 unsafe impl Pod for UserRBAC {}
 
+// Original slab element, converted to network-wide non-expiring grants by migrate_rbac
+#[derive(Copy, Clone)]
+#[repr(packed)]
+pub struct UserRBACV0 {
+    pub role: u32,
+    pub free: u32,
+}
+unsafe impl Zeroable for UserRBACV0 {}
+unsafe impl Pod for UserRBACV0 {}
+
+impl UserRBACV0 {
+    pub fn convert(&self) -> anchor_lang::Result<UserRBAC> {
+        let role = Role::try_from_primitive(self.role).map_err(|_| error!(ErrorCode::InternalError))?;
+        Ok(UserRBAC {
+            role: role,
+            free: 0,
+            scoped: 0,
+            catalog: 0,
+            valid_from: 0,
+            expires: 0,
+        })
+    }
+}
+
 impl UserRBAC {
     pub fn role(&self) -> Role {
         self.role
@@ -56,6 +124,24 @@ impl UserRBAC {
         self.free
     }
 
+    pub fn active(&self, now: i64) -> bool {
+        let valid_from = self.valid_from;
+        (valid_from == 0 || now >= valid_from) && ! self.expired(now)
+    }
+
+    pub fn expired(&self, now: i64) -> bool {
+        let expires = self.expires;
+        expires != 0 && now >= expires
+    }
+
+    pub fn catalog(&self) -> Option<u64> {
+        if self.scoped != 0 {
+            Some(self.catalog)
+        } else {
+            None
+        }
+    }
+
     pub fn set_free(&mut self, new_free: u32) {
         self.free = new_free
     }
@@ -88,19 +174,89 @@ fn full_account_zero(account: &AccountInfo) -> bool {
     prefix.iter().all(|&x| x == 0) && suffix.iter().all(|&x| x == 0) && aligned.iter().all(|&x| x == 0)
 }
 
-#[inline]
-fn index_datatype(data_type: DT) -> u16 {  // Maps only
-    match data_type {
-        DT::UserRBAC => DT::UserRBAC as u16,
-        _ => { panic!("Invalid datatype") },
+fn shake128_hash(data: &[u8]) -> u128 {
+    let mut hasher = Shake128::default();
+    hasher.update(data);
+    let mut reader = hasher.finalize_xof();
+    let mut hash_result = [0u8; 16];
+    reader.read(&mut hash_result);
+    u128::from_be_bytes(hash_result)
+}
+
+// Fails if any of the flags are paused network-wide or for the catalog
+fn check_pause(root_data: &RootData, catalog: Option<&CatalogInstance>, flags: u8) -> anchor_lang::Result<()> {
+    if root_data.paused & flags != 0 {
+        msg!("Network paused");
+        return Err(ErrorCode::Paused.into());
+    }
+    if catalog.map_or(false, |c| c.paused & flags != 0) {
+        msg!("Catalog paused");
+        return Err(ErrorCode::Paused.into());
     }
+    Ok(())
+}
+
+fn emit_pause(user: &Pubkey, catalog: Option<u64>, flags: u8, paused: u8, pause: bool) {
+    if pause {
+        msg!("Atellix: Paused");
+        emit!(PausedEvent { user: *user, catalog: catalog, flags: flags, paused: paused });
+    } else {
+        msg!("Atellix: Unpaused");
+        emit!(UnpausedEvent { user: *user, catalog: catalog, flags: flags, paused: paused });
+    }
+}
+
+// Upgrade authority, or the stored super-admin once the program is immutable
+fn is_program_owner(program_data: &ProgramData, root_data: &RootData, key: &Pubkey) -> bool {
+    match program_data.upgrade_authority_address {
+        Some(authority) => authority == *key,
+        None => root_data.super_admin == *key,
+    }
+}
+
+// Grow a program account to 'new_len', topping up rent from 'payer'
+fn realloc_account<'info>(acc: &AccountInfo<'info>, payer: &AccountInfo<'info>, system_program: &AccountInfo<'info>, new_len: usize) -> anchor_lang::Result<()> {
+    let rent = Rent::get()?.minimum_balance(new_len);
+    let lamports = acc.lamports();
+    if rent > lamports {
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: payer.clone(),
+            to: acc.clone(),
+        };
+        let cpi_ctx = CpiContext::new(system_program.clone(), cpi_accounts);
+        anchor_lang::system_program::transfer(cpi_ctx, rent - lamports)?;
+    }
+    acc.realloc(new_len, false)?;
+    Ok(())
+}
+
+fn update_url_refs(acc_url: &AccountInfo, increment: bool) -> anchor_lang::Result<()> {
+    require!(*acc_url.owner == crate::ID, ErrorCode::InvalidURLAccount);
+    let url_data: &mut [u8] = &mut acc_url.try_borrow_mut_data()?;
+    if CatalogUrl::is_legacy(url_data) {
+        msg!("Atellix: Legacy URL, references not tracked");
+        return Ok(());
+    }
+    let mut url_entry = CatalogUrl::try_deserialize(&mut &url_data[..])?;
+    require!(url_entry.ready, ErrorCode::URLNotReady);
+    if url_entry.version == URL_VERSION_UNTRACKED {
+        return Ok(());
+    }
+    if increment {
+        url_entry.ref_count = url_entry.ref_count.checked_add(1).ok_or(error!(ErrorCode::Overflow))?;
+    } else {
+        url_entry.ref_count = url_entry.ref_count.checked_sub(1).ok_or(error!(ErrorCode::Overflow))?;
+    }
+    let mut writer: &mut [u8] = url_data;
+    url_entry.try_serialize(&mut writer)?;
+    Ok(())
 }
 
 #[inline]
-fn map_len(data_type: DT) -> u32 {
+fn index_datatype(data_type: DT) -> u16 {  // Maps only
     match data_type {
-        DT::UserRBAC => MAX_RBAC,
-        _ => 0,
+        DT::UserRBAC => DT::UserRBAC as u16,
+        _ => { panic!("Invalid datatype") },
     }
 }
 
@@ -113,8 +269,8 @@ fn map_datatype(data_type: DT) -> u16 {  // Maps only
 }
 
 #[inline]
-fn map_get(pt: &mut SlabPageAlloc, data_type: DT, key: u128) -> Option<LeafNode> {
-    let cm = CritMap { slab: pt, type_id: map_datatype(data_type), capacity: map_len(data_type) };
+fn map_get(pt: &mut SlabPageAlloc, data_type: DT, capacity: u32, key: u128) -> Option<LeafNode> {
+    let cm = CritMap { slab: pt, type_id: map_datatype(data_type), capacity: capacity };
     let res = cm.get_key(key);
     match res {
         None => None,
@@ -123,8 +279,8 @@ fn map_get(pt: &mut SlabPageAlloc, data_type: DT, key: u128) -> Option<LeafNode>
 }
 
 #[inline]
-fn map_insert(pt: &mut SlabPageAlloc, data_type: DT, node: &LeafNode) -> FnResult<(), SlabTreeError> {
-    let mut cm = CritMap { slab: pt, type_id: map_datatype(data_type), capacity: map_len(data_type) };
+fn map_insert(pt: &mut SlabPageAlloc, data_type: DT, capacity: u32, node: &LeafNode) -> FnResult<(), SlabTreeError> {
+    let mut cm = CritMap { slab: pt, type_id: map_datatype(data_type), capacity: capacity };
     let res = cm.insert_leaf(node);
     match res {
         Err(SlabTreeError::OutOfSpace) => {
@@ -136,17 +292,403 @@ fn map_insert(pt: &mut SlabPageAlloc, data_type: DT, node: &LeafNode) -> FnResul
 }
 
 #[inline]
-fn map_remove(pt: &mut SlabPageAlloc, data_type: DT, key: u128) -> FnResult<(), SlabTreeError> {
-    let mut cm = CritMap { slab: pt, type_id: map_datatype(data_type), capacity: map_len(data_type) };
+fn map_remove(pt: &mut SlabPageAlloc, data_type: DT, capacity: u32, key: u128) -> FnResult<(), SlabTreeError> {
+    let mut cm = CritMap { slab: pt, type_id: map_datatype(data_type), capacity: capacity };
     cm.remove_by_key(key).ok_or(SlabTreeError::NotFound)?;
     Ok(())
 }
 
-fn has_role(acc_auth: &AccountInfo, role: Role, key: &Pubkey) -> anchor_lang::Result<()> {
+// Scan map nodes starting at 'cursor' and return up to 'limit' grants
+fn role_page(rd: &mut SlabPageAlloc, capacity: u32, cursor: u32, limit: u32) -> RoleList {
+    let mut roles: Vec<RoleEntry> = Vec::new();
+    let mut idx = cursor;
+    while idx < capacity && (roles.len() as u32) < limit {
+        if let Some(leaf) = rd.index::<AnyNode>(DT::UserRBACMap as u16, idx as usize).as_leaf() {
+            let urec = rd.index::<UserRBAC>(DT::UserRBAC as u16, leaf.slot() as usize);
+            roles.push(RoleEntry {
+                user: leaf.owner(),
+                role: urec.role() as u32,
+                catalog: urec.catalog(),
+                valid_from: urec.valid_from,
+                expires: urec.expires,
+            });
+        }
+        idx += 1;
+    }
+    RoleList {
+        next_cursor: if idx < capacity { Some(idx) } else { None },
+        roles: roles,
+    }
+}
+
+// Add or renew a grant, emits RoleGrantedEvent or RoleUnchangedEvent
+fn grant_role(acc_auth: &AccountInfo, capacity: u32, admin: &Pubkey, program_owner: bool, entry: &RoleEntry) -> anchor_lang::Result<()> {
+    let role = Role::try_from_primitive(entry.role).map_err(|_| error!(ErrorCode::InvalidParameters))?;
+
+    let now = Clock::get()?.unix_timestamp;
+    check_validity(now, entry.valid_from, entry.expires)?;
+
+    let auth_data: &mut[u8] = &mut acc_auth.try_borrow_mut_data()?;
+    let rd = SlabPageAlloc::new(auth_data);
+    let authhash: u128 = role_hash(role, &entry.user, entry.catalog);
+
+    // Check if record exists
+    let authrec = map_get(rd, DT::UserRBAC, capacity, authhash);
+    let slot: u32;
+    if authrec.is_some() {
+        slot = authrec.unwrap().slot();
+        let urec = rd.index_mut::<UserRBAC>(DT::UserRBAC as u16, slot as usize);
+        if ! urec.expired(now) {
+            msg!("Atellix: Role already active");
+            emit!(RoleUnchangedEvent {
+                admin: *admin,
+                user: entry.user,
+                role: entry.role,
+                catalog: entry.catalog,
+                program_owner: program_owner,
+                grant: true,
+            });
+            return Ok(());
+        }
+        // Renew expired grants that have not been pruned yet
+        urec.valid_from = entry.valid_from;
+        urec.expires = entry.expires;
+    } else {
+        // Add new record
+        slot = insert_role(rd, capacity, authhash, &entry.user, UserRBAC {
+            role: role,
+            free: 0,
+            scoped: entry.catalog.is_some() as u8,
+            catalog: entry.catalog.unwrap_or(0),
+            valid_from: entry.valid_from,
+            expires: entry.expires,
+        })?;
+    }
+    msg!("Atellix: Role granted");
+    emit!(RoleGrantedEvent {
+        admin: *admin,
+        user: entry.user,
+        role: entry.role,
+        catalog: entry.catalog,
+        program_owner: program_owner,
+        slot: slot,
+        valid_from: entry.valid_from,
+        expires: entry.expires,
+    });
+    Ok(())
+}
+
+// Remove a grant, emits RoleRevokedEvent or RoleUnchangedEvent
+fn revoke_role(acc_auth: &AccountInfo, capacity: u32, admin: &Pubkey, program_owner: bool, role: Role, user: &Pubkey, catalog: Option<u64>) -> anchor_lang::Result<()> {
+    let auth_data: &mut[u8] = &mut acc_auth.try_borrow_mut_data()?;
+    let rd = SlabPageAlloc::new(auth_data);
+    let authhash: u128 = role_hash(role, user, catalog);
+
+    // Check if record exists
+    let authrec = map_get(rd, DT::UserRBAC, capacity, authhash);
+    if authrec.is_some() {
+        let slot = authrec.unwrap().slot();
+        map_remove(rd, DT::UserRBAC, capacity, authhash).or(Err(error!(ErrorCode::InternalError)))?;
+        UserRBAC::free_index(rd, DT::UserRBAC, slot)?;
+        msg!("Atellix: Role revoked");
+        emit!(RoleRevokedEvent {
+            admin: *admin,
+            user: *user,
+            role: role as u32,
+            catalog: catalog,
+            program_owner: program_owner,
+            slot: slot,
+        });
+    } else {
+        msg!("Atellix: Role not found");
+        emit!(RoleUnchangedEvent {
+            admin: *admin,
+            user: *user,
+            role: role as u32,
+            catalog: catalog,
+            program_owner: program_owner,
+            grant: false,
+        });
+    }
+    Ok(())
+}
+
+fn setup_rbac(acc_auth: &AccountInfo, capacity: u32) -> anchor_lang::Result<()> {
+    let auth_data: &mut[u8] = &mut acc_auth.try_borrow_mut_data()?;
+    let rd = SlabPageAlloc::new(auth_data);
+    rd.setup_page_table();
+    rd.allocate::<CritMapHeader, AnyNode>(DT::UserRBACMap as u16, capacity as usize).or(Err(error!(ErrorCode::InternalError)))?;
+    rd.allocate::<SlabVec, UserRBAC>(DT::UserRBAC as u16, capacity as usize).or(Err(error!(ErrorCode::InternalError)))?;
+    Ok(())
+}
+
+fn insert_role(rd: &mut SlabPageAlloc, capacity: u32, authhash: u128, user: &Pubkey, record: UserRBAC) -> anchor_lang::Result<u32> {
+    let new_item = map_insert(rd, DT::UserRBAC, capacity, &LeafNode::new(authhash, 0, user));
+    if new_item.is_err() {
+        msg!("Unable to insert role");
+        return Err(ErrorCode::InternalError.into());
+    }
+    let rbac_idx = UserRBAC::next_index(rd, DT::UserRBAC)?;
+    let mut cm = CritMap { slab: rd, type_id: map_datatype(DT::UserRBAC), capacity: capacity };
+    cm.get_key_mut(authhash).unwrap().set_slot(rbac_idx);
+    *rd.index_mut(DT::UserRBAC as u16, rbac_idx as usize) = record;
+    Ok(rbac_idx)
+}
+
+// Copy up to 'limit' grants found in map nodes starting at 'cursor', returns the next cursor and the number of grants copied
+// Grants in a 'src_version' 0 account are converted from UserRBACV0 (the map keys are unchanged)
+fn copy_rbac(acc_src: &AccountInfo, src_capacity: u32, src_version: u8, acc_dst: &AccountInfo, dst_capacity: u32, cursor: u32, limit: u32) -> anchor_lang::Result<(u32, u32)> {
+    let src_data: &mut[u8] = &mut acc_src.try_borrow_mut_data()?;
+    let dst_data: &mut[u8] = &mut acc_dst.try_borrow_mut_data()?;
+    let sd = SlabPageAlloc::new(src_data);
+    let dd = SlabPageAlloc::new(dst_data);
+    let mut count: u32 = 0;
+    let mut idx = cursor;
+    while idx < src_capacity && count < limit {
+        let leaf = sd.index::<AnyNode>(DT::UserRBACMap as u16, idx as usize).as_leaf().cloned();
+        idx += 1;
+        if let Some(leaf) = leaf {
+            let record = if src_version == 0 {
+                sd.index::<UserRBACV0>(DT::UserRBAC as u16, leaf.slot() as usize).convert()?
+            } else {
+                *sd.index::<UserRBAC>(DT::UserRBAC as u16, leaf.slot() as usize)
+            };
+            insert_role(dd, dst_capacity, leaf.key(), &leaf.owner(), record)?;
+            count = count.checked_add(1).ok_or(error!(ErrorCode::Overflow))?;
+        }
+    }
+    Ok((idx, count))
+}
+
+fn close_account(acc: &AccountInfo, acc_recipient: &AccountInfo) -> anchor_lang::Result<()> {
+    let lamports = acc.lamports();
+    **acc_recipient.try_borrow_mut_lamports()? = acc_recipient.lamports().checked_add(lamports).ok_or(error!(ErrorCode::Overflow))?;
+    **acc.try_borrow_mut_lamports()? = 0;
+    acc.try_borrow_mut_data()?.fill(0);
+    Ok(())
+}
+
+fn count_roles(rd: &mut SlabPageAlloc, capacity: u32) -> u32 {
+    (0..capacity).filter(|i| rd.index::<AnyNode>(DT::UserRBACMap as u16, *i as usize).as_leaf().is_some()).count() as u32
+}
+
+// Verify the RoleGrant address for a grant and return the bump
+fn role_grant_bump(acc_grant: &AccountInfo, role: u32, user: &Pubkey, catalog: Option<u64>) -> anchor_lang::Result<u8> {
+    let (grant_key, bump) = Pubkey::find_program_address(&[b"role", role.to_be_bytes().as_ref(), user.as_ref(), role_scope(catalog).as_ref()], &crate::ID);
+    if grant_key != *acc_grant.key {
+        msg!("Invalid role grant account");
+        return Err(ErrorCode::InvalidParameters.into());
+    }
+    Ok(bump)
+}
+
+// RBACMode::Accounts version of grant_role, creates or renews the RoleGrant account
+fn grant_role_account_data<'info>(acc_grant: &AccountInfo<'info>, acc_payer: &AccountInfo<'info>, acc_system: &AccountInfo<'info>, admin: &Pubkey, program_owner: bool, entry: &RoleEntry) -> anchor_lang::Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    check_validity(now, entry.valid_from, entry.expires)?;
+    let bump = role_grant_bump(acc_grant, entry.role, &entry.user, entry.catalog)?;
+    let mut payer = acc_payer.key();
+    if *acc_grant.owner == crate::ID {
+        let grant_data: &[u8] = &acc_grant.try_borrow_data()?;
+        let grant = RoleGrant::try_deserialize(&mut &grant_data[..])?;
+        if ! grant.expired(now) {
+            msg!("Atellix: Role already active");
+            emit!(RoleUnchangedEvent {
+                admin: *admin,
+                user: entry.user,
+                role: entry.role,
+                catalog: entry.catalog,
+                program_owner: program_owner,
+                grant: true,
+            });
+            return Ok(());
+        }
+        // Renew expired grants that have not been pruned yet
+        payer = grant.payer;
+    } else {
+        let role_bytes = entry.role.to_be_bytes();
+        let scope = role_scope(entry.catalog);
+        let seeds = &[b"role".as_ref(), role_bytes.as_ref(), entry.user.as_ref(), scope.as_ref(), &[bump]];
+        let signer = &[&seeds[..]];
+        let rent = Rent::get()?.minimum_balance(101);
+        let lamports = acc_grant.lamports();
+        if lamports == 0 {
+            let cpi_accounts = anchor_lang::system_program::CreateAccount {
+                from: acc_payer.clone(),
+                to: acc_grant.clone(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(acc_system.clone(), cpi_accounts, signer);
+            anchor_lang::system_program::create_account(cpi_ctx, rent, 101, &crate::ID)?;
+        } else {
+            // Already funded (same as Anchor's init), top up the rent then allocate and assign the address
+            let required = rent.saturating_sub(lamports);
+            if required > 0 {
+                let cpi_accounts = anchor_lang::system_program::Transfer {
+                    from: acc_payer.clone(),
+                    to: acc_grant.clone(),
+                };
+                let cpi_ctx = CpiContext::new(acc_system.clone(), cpi_accounts);
+                anchor_lang::system_program::transfer(cpi_ctx, required)?;
+            }
+            let cpi_accounts = anchor_lang::system_program::Allocate {
+                account_to_allocate: acc_grant.clone(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(acc_system.clone(), cpi_accounts, signer);
+            anchor_lang::system_program::allocate(cpi_ctx, 101)?;
+            let cpi_accounts = anchor_lang::system_program::Assign {
+                account_to_assign: acc_grant.clone(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(acc_system.clone(), cpi_accounts, signer);
+            anchor_lang::system_program::assign(cpi_ctx, &crate::ID)?;
+        }
+    }
+    let grant = RoleGrant {
+        role: entry.role,
+        user: entry.user,
+        catalog: entry.catalog,
+        valid_from: entry.valid_from,
+        expires: entry.expires,
+        payer: payer,
+    };
+    let grant_data: &mut [u8] = &mut acc_grant.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = grant_data;
+    grant.try_serialize(&mut writer)?;
+    msg!("Atellix: Role granted");
+    emit!(RoleGrantedEvent {
+        admin: *admin,
+        user: entry.user,
+        role: entry.role,
+        catalog: entry.catalog,
+        program_owner: program_owner,
+        slot: ROLE_GRANT_SLOT,
+        valid_from: entry.valid_from,
+        expires: entry.expires,
+    });
+    Ok(())
+}
+
+// RBACMode::Accounts version of revoke_role, closes the RoleGrant account to its payer 'acc_recipient'
+fn revoke_role_account_data(acc_grant: &AccountInfo, acc_recipient: &AccountInfo, admin: &Pubkey, program_owner: bool, role: Role, user: &Pubkey, catalog: Option<u64>) -> anchor_lang::Result<()> {
+    role_grant_bump(acc_grant, role as u32, user, catalog)?;
+    if *acc_grant.owner != crate::ID || acc_grant.data_is_empty() {
+        msg!("Atellix: Role not found");
+        emit!(RoleUnchangedEvent {
+            admin: *admin,
+            user: *user,
+            role: role as u32,
+            catalog: catalog,
+            program_owner: program_owner,
+            grant: false,
+        });
+        return Ok(());
+    }
+    let payer = {
+        let grant_data: &[u8] = &acc_grant.try_borrow_data()?;
+        RoleGrant::try_deserialize(&mut &grant_data[..])?.payer
+    };
+    require!(payer == *acc_recipient.key, ErrorCode::InvalidParameters);
+    close_account(acc_grant, acc_recipient)?;
+    msg!("Atellix: Role revoked");
+    emit!(RoleRevokedEvent {
+        admin: *admin,
+        user: *user,
+        role: role as u32,
+        catalog: catalog,
+        program_owner: program_owner,
+        slot: ROLE_GRANT_SLOT,
+    });
+    Ok(())
+}
+
+fn role_hash(role: Role, key: &Pubkey, catalog: Option<u64>) -> u128 {
+    match catalog {
+        Some(catalog_id) => CritMap::bytes_hash([[role as u32].as_byte_slice(), key.as_ref(), catalog_id.to_be_bytes().as_ref()].concat().as_slice()),
+        None => CritMap::bytes_hash([[role as u32].as_byte_slice(), key.as_ref()].concat().as_slice()),
+    }
+}
+
+// Roles managed by the program owner (and threshold or role admin changes) can be approved by the owner or NetworkAdmin holders
+fn proposal_admin_role(root_data: &RootData, action: ProposalAction, role: Role) -> Option<Role> {
+    match action {
+        ProposalAction::SetThreshold | ProposalAction::SetRoleAdmin => None,
+        _ => root_data.role_admin(role),
+    }
+}
+
+// 'acc_auths' is the root authority slab, or RoleGrant accounts in RBACMode::Accounts
+fn is_admin_approver(root_data: &RootData, program_data: &ProgramData, acc_auths: &[AccountInfo], admin_role: Option<Role>, key: &Pubkey, catalog: Option<u64>) -> bool {
+    is_program_owner(program_data, root_data, key) ||
+        acc_auths.iter().any(|acc_auth| check_role(root_data, acc_auth, admin_role.unwrap_or(Role::NetworkAdmin), key, catalog).is_ok())
+}
+
+fn check_validity(now: i64, valid_from: i64, expires: i64) -> anchor_lang::Result<()> {
+    if expires != 0 && (expires <= now || expires <= valid_from) {
+        msg!("Invalid expiration");
+        return Err(ErrorCode::InvalidParameters.into());
+    }
+    Ok(())
+}
+
+// Seed for RoleGrant accounts: [0; 9] for network-wide grants, [1, catalog...] for catalog grants
+pub fn role_scope(catalog: Option<u64>) -> [u8; 9] {
+    let mut scope = [0u8; 9];
+    if let Some(catalog_id) = catalog {
+        scope[0] = 1;
+        scope[1..].copy_from_slice(&catalog_id.to_be_bytes());
+    }
+    scope
+}
+
+// Role check for instructions that support both RBAC modes, 'acc_auth' is the root authority slab or a RoleGrant account
+fn check_role(root_data: &RootData, acc_auth: &AccountInfo, role: Role, key: &Pubkey, catalog: Option<u64>) -> anchor_lang::Result<()> {
+    match root_data.rbac_mode()? {
+        RBACMode::Slab | RBACMode::Migrating => {
+            if root_data.root_authority != *acc_auth.key {
+                msg!("Invalid root authority");
+                return Err(ErrorCode::AccessDenied.into());
+            }
+            root_data.require_rbac_version()?;
+            has_role(acc_auth, root_data.rbac_capacity, role, key, catalog)
+        },
+        RBACMode::Accounts => {
+            if *acc_auth.owner != crate::ID {
+                msg!("Invalid role grant");
+                return Err(ErrorCode::AccessDenied.into());
+            }
+            let grant_data: &[u8] = &acc_auth.try_borrow_data()?;
+            let grant = RoleGrant::try_deserialize(&mut &grant_data[..]).map_err(|_| error!(ErrorCode::AccessDenied))?;
+            if grant.user != *key || grant.role != role as u32 {
+                msg!("Role does not match");
+                return Err(ErrorCode::AccessDenied.into());
+            }
+            if grant.catalog.is_some() && grant.catalog != catalog {
+                msg!("Role scope does not match");
+                return Err(ErrorCode::AccessDenied.into());
+            }
+            if ! grant.active(Clock::get()?.unix_timestamp) {
+                msg!("Role expired or not yet valid");
+                return Err(ErrorCode::AccessDenied.into());
+            }
+            Ok(())
+        },
+    }
+}
+
+// Network-wide grants apply to every catalog, scoped grants only to the specified catalog
+// An inactive network-wide grant does not hide an active grant scoped to 'catalog'
+fn has_role(acc_auth: &AccountInfo, capacity: u32, role: Role, key: &Pubkey, catalog: Option<u64>) -> anchor_lang::Result<()> {
     let auth_data: &mut [u8] = &mut acc_auth.try_borrow_mut_data()?;
     let rd = SlabPageAlloc::new(auth_data);
-    let authhash: u128 = CritMap::bytes_hash([[role as u32].as_byte_slice(), key.as_ref()].concat().as_slice());
-    let authrec = map_get(rd, DT::UserRBAC, authhash);
+    let now = Clock::get()?.unix_timestamp;
+    let network_role = check_grant(rd, capacity, role_hash(role, key, None), role, key, catalog, now);
+    if network_role.is_err() && catalog.is_some() {
+        return check_grant(rd, capacity, role_hash(role, key, catalog), role, key, catalog, now);
+    }
+    network_role
+}
+
+fn check_grant(rd: &mut SlabPageAlloc, capacity: u32, authhash: u128, role: Role, key: &Pubkey, catalog: Option<u64>, now: i64) -> anchor_lang::Result<()> {
+    let authrec = map_get(rd, DT::UserRBAC, capacity, authhash);
     if ! authrec.is_some() {
         return Err(ErrorCode::AccessDenied.into());
     }
@@ -159,15 +701,44 @@ fn has_role(acc_auth: &AccountInfo, role: Role, key: &Pubkey) -> anchor_lang::Re
         msg!("Role does not match");
         return Err(ErrorCode::AccessDenied.into());
     }
+    if urec.catalog().is_some() && urec.catalog() != catalog {
+        msg!("Role scope does not match");
+        return Err(ErrorCode::AccessDenied.into());
+    }
+    if ! urec.active(now) {
+        msg!("Role expired or not yet valid");
+        return Err(ErrorCode::AccessDenied.into());
+    }
     Ok(())
 }
 
 #[repr(u8)]
-#[derive(PartialEq, Debug, Eq, Copy, Clone)] // TryFromPrimitive
+#[derive(PartialEq, Debug, Eq, Copy, Clone, TryFromPrimitive)]
 pub enum URLExpandMode {
     None,           // 0 - Do not expand (full URL provided)
     AppendUUID,     // 1 - Convert the 'uuid' field to a lowercase UUID and then append the UUID to the URL
     UTF8UriEncoded, // 2 - URI-encoded UTF-8 string
+    Template,       // 3 - Replace {uuid}, {catalog}, {listing_idx} and {owner} placeholders with listing fields
+    IPFSContent,    // 4 - IPFS CID (v0 or base32 v1) stored as binary in 'url_data'
+    ArweaveTx,      // 5 - Arweave transaction id stored as 32 bytes in 'url_data'
+}
+
+impl URLExpandMode {
+    /// Check that a URL is well-formed for this expand mode
+    pub fn validate_url(&self, url: &str) -> bool {
+        match self {
+            URLExpandMode::None => true,
+            URLExpandMode::AppendUUID => url.ends_with(|c| matches!(c, '/' | '=' | '?' | '#' | '&')),
+            URLExpandMode::UTF8UriEncoded => utils::percent_decode(url).map_or(false, |v| std::str::from_utf8(&v).is_ok()),
+            URLExpandMode::Template => utils::expand_template(url, |f| utils::URL_TEMPLATE_FIELDS.contains(&f).then(String::new)).is_some(),
+            URLExpandMode::IPFSContent | URLExpandMode::ArweaveTx => utils::content_id_to_bytes(*self, url).is_some(),
+        }
+    }
+
+    /// Binary modes store the decoded content id in 'url_data' instead of 'url'
+    pub fn is_binary(&self) -> bool {
+        matches!(self, URLExpandMode::IPFSContent | URLExpandMode::ArweaveTx)
+    }
 }
 
 #[derive(BorshSerialize, BorshDeserialize)]
@@ -191,6 +762,44 @@ pub struct CatalogParameters {
 
 // LEN: 16 + 8 + 16 + 16 + 16 + 16 + 1 + 4 + 4 + 32 + 32 + 32 + 32 + 32 + 8 = 265
 
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ListingUpdateParameters {
+    pub listing: CatalogParameters,
+    pub update_seq: u64,        // Must equal the listing's update_count, prevents replaying older updates (publish_update does not change it)
+}
+
+// LEN: 265 + 8 = 273
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct RoleEntry {
+    pub user: Pubkey,
+    pub role: u32,
+    pub catalog: Option<u64>,
+    pub valid_from: i64,
+    pub expires: i64,
+}
+// LEN: 32 + 4 + 9 + 8 + 8 = 61
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct CatalogSigner {
+    pub key: Pubkey,            // Default key = unused entry
+    pub not_before: i64,        // 0 = valid immediately
+    pub not_after: i64,         // 0 = never expires
+}
+// LEN: 32 + 8 + 8 = 48
+
+impl CatalogSigner {
+    pub fn active(&self, now: i64) -> bool {
+        self.key != Pubkey::default() && (self.not_before == 0 || now >= self.not_before) && (self.not_after == 0 || now < self.not_after)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct RoleList {
+    pub next_cursor: Option<u32>,   // None when the end of the map was reached
+    pub roles: Vec<RoleEntry>,
+}
+
 #[program]
 pub mod catalog {
     use super::*;
@@ -199,13 +808,39 @@ pub mod catalog {
         let rt = &mut ctx.accounts.root_data;
         rt.catalog_count = 0;
         rt.root_authority = ctx.accounts.auth_data.key();
+        rt.rbac_capacity = MAX_RBAC;
+        rt.admin_threshold = 0;
+        rt.role_admin = [Role::NetworkAdmin as u32; MAX_ROLES];
+        rt.role_admin[Role::NetworkAdmin as usize] = ROLE_ADMIN_OWNER;
+        rt.super_admin = ctx.accounts.program_admin.key();
+        rt.pending_super_admin = Pubkey::default();
+        rt.paused = 0;
+        rt.rbac_mode = RBACMode::Slab as u8;
+        rt.rbac_version = RBAC_VERSION;
+
+        setup_rbac(&ctx.accounts.auth_data.to_account_info(), MAX_RBAC)?;
 
-        let auth_data: &mut[u8] = &mut ctx.accounts.auth_data.try_borrow_mut_data()?;
-        let rd = SlabPageAlloc::new(auth_data);
-        rd.setup_page_table();
-        rd.allocate::<CritMapHeader, AnyNode>(DT::UserRBACMap as u16, MAX_RBAC as usize).expect("Failed to allocate");
-        rd.allocate::<SlabVec, UserRBAC>(DT::UserRBAC as u16, MAX_RBAC as usize).expect("Failed to allocate");
+        Ok(())
+    }
+
+    // Step 1 of transferring the super-admin, the new key must call accept_super_admin
+    pub fn transfer_super_admin(ctx: Context<TransferSuperAdmin>,
+        inp_new_admin: Pubkey,
+    ) -> anchor_lang::Result<()> {
+        ctx.accounts.root_data.pending_super_admin = inp_new_admin;
+        msg!("Atellix: Pending super admin: {}", inp_new_admin.to_string());
+        Ok(())
+    }
 
+    pub fn accept_super_admin(ctx: Context<AcceptSuperAdmin>) -> anchor_lang::Result<()> {
+        let root_data = &mut ctx.accounts.root_data;
+        let prev_admin = root_data.super_admin;
+        root_data.super_admin = root_data.pending_super_admin;
+        root_data.pending_super_admin = Pubkey::default();
+        emit!(SuperAdminUpdatedEvent {
+            prev_admin: prev_admin,
+            new_admin: root_data.super_admin,
+        });
         Ok(())
     }
 
@@ -239,20 +874,16 @@ pub mod catalog {
     pub fn grant(ctx: Context<UpdateRBAC>,
         _inp_root_nonce: u8,
         inp_role: u32,
+        inp_catalog: Option<u64>,
+        inp_valid_from: i64,
+        inp_expires: i64,
     ) -> anchor_lang::Result<()> {
+        ctx.accounts.root_data.require_rbac_mode(RBACMode::Slab)?;
+        ctx.accounts.root_data.require_rbac_idle()?;
         let acc_rbac = &ctx.accounts.rbac_user.to_account_info();
         let acc_admn = &ctx.accounts.program_admin.to_account_info();
         let acc_auth = &ctx.accounts.auth_data.to_account_info();
 
-        // Check for NetworkAdmin authority
-        let admin_role = has_role(&acc_auth, Role::NetworkAdmin, acc_admn.key);
-        let mut program_owner: bool = false;
-        if admin_role.is_err() {
-            let acc_pdat = &ctx.accounts.program_data;
-            require!(acc_pdat.upgrade_authority_address.unwrap() == *acc_admn.key, ErrorCode::AccessDenied);
-            program_owner = true;
-        }
-
         // Verify specified role
         let role_item = Role::try_from_primitive(inp_role);
         if role_item.is_err() {
@@ -260,8 +891,17 @@ pub mod catalog {
             return Err(ErrorCode::InvalidParameters.into());
         }
         let role = role_item.unwrap();
-        if role == Role::NetworkAdmin && ! program_owner {
-            msg!("Reserved for program owner");
+
+        // Check for the admin role of the specified role
+        let capacity = ctx.accounts.root_data.rbac_capacity;
+        let admin_role = ctx.accounts.root_data.role_admin(role);
+        let mut program_owner: bool = false;
+        if admin_role.map_or(true, |r| has_role(&acc_auth, capacity, r, acc_admn.key, inp_catalog).is_err()) {
+            require!(is_program_owner(&ctx.accounts.program_data, &ctx.accounts.root_data, acc_admn.key), ErrorCode::AccessDenied);
+            program_owner = true;
+        }
+        if ctx.accounts.root_data.requires_multisig(admin_role, program_owner) {
+            msg!("Multisig approval required");
             return Err(ErrorCode::AccessDenied.into());
         }
 
@@ -271,47 +911,26 @@ pub mod catalog {
             return Err(ErrorCode::AccessDenied.into());
         }
 
-        let auth_data: &mut[u8] = &mut acc_auth.try_borrow_mut_data()?;
-        let rd = SlabPageAlloc::new(auth_data);
-        let authhash: u128 = CritMap::bytes_hash([[role as u32].as_byte_slice(), acc_rbac.key.as_ref()].concat().as_slice());
-
-        // Check if record exists
-        let authrec = map_get(rd, DT::UserRBAC, authhash);
-        if authrec.is_some() {
-            msg!("Atellix: Role already active");
-        } else {
-            // Add new record
-            let new_item = map_insert(rd, DT::UserRBAC, &LeafNode::new(authhash, 0, acc_rbac.key));
-            if new_item.is_err() {
-                msg!("Unable to insert role");
-                return Err(ErrorCode::InternalError.into());
-            }
-            let rbac_idx = UserRBAC::next_index(rd, DT::UserRBAC)?;
-            let mut cm = CritMap { slab: rd, type_id: map_datatype(DT::UserRBAC), capacity: map_len(DT::UserRBAC) };
-            cm.get_key_mut(authhash).unwrap().set_slot(rbac_idx);
-            *rd.index_mut(DT::UserRBAC as u16, rbac_idx as usize) = UserRBAC { role: role, free: 0 };
-            msg!("Atellix: Role granted");
-        }
-        Ok(())
+        grant_role(acc_auth, capacity, acc_admn.key, program_owner, &RoleEntry {
+            user: acc_rbac.key(),
+            role: inp_role,
+            catalog: inp_catalog,
+            valid_from: inp_valid_from,
+            expires: inp_expires,
+        })
     }
 
     pub fn revoke(ctx: Context<UpdateRBAC>,
         _inp_root_nonce: u8,
         inp_role: u32,
+        inp_catalog: Option<u64>,
     ) -> anchor_lang::Result<()> {
-        let acc_admn = &ctx.accounts.program_admin.to_account_info(); // Program owner or network admin
+        ctx.accounts.root_data.require_rbac_mode(RBACMode::Slab)?;
+        ctx.accounts.root_data.require_rbac_idle()?;
+        let acc_admn = &ctx.accounts.program_admin.to_account_info(); // Program owner or admin of the role
         let acc_auth = &ctx.accounts.auth_data.to_account_info();
         let acc_rbac = &ctx.accounts.rbac_user.to_account_info();
 
-        // Check for NetworkAdmin authority
-        let admin_role = has_role(&acc_auth, Role::NetworkAdmin, acc_admn.key);
-        let mut program_owner: bool = false;
-        if admin_role.is_err() {
-            let acc_pdat = &ctx.accounts.program_data;
-            require!(acc_pdat.upgrade_authority_address.unwrap() == *acc_admn.key, ErrorCode::AccessDenied);
-            program_owner = true;
-        }
-
         // Verify specified role
         let role_item = Role::try_from_primitive(inp_role);
         if role_item.is_err() {
@@ -319,88 +938,1164 @@ pub mod catalog {
             return Err(ErrorCode::InvalidParameters.into());
         }
         let role = role_item.unwrap();
-        if role == Role::NetworkAdmin && ! program_owner {
-            msg!("Reserved for program owner");
+
+        // Check for the admin role of the specified role
+        let capacity = ctx.accounts.root_data.rbac_capacity;
+        let admin_role = ctx.accounts.root_data.role_admin(role);
+        let mut program_owner: bool = false;
+        if admin_role.map_or(true, |r| has_role(&acc_auth, capacity, r, acc_admn.key, inp_catalog).is_err()) {
+            require!(is_program_owner(&ctx.accounts.program_data, &ctx.accounts.root_data, acc_admn.key), ErrorCode::AccessDenied);
+            program_owner = true;
+        }
+        if ctx.accounts.root_data.requires_multisig(admin_role, program_owner) {
+            msg!("Multisig approval required");
             return Err(ErrorCode::AccessDenied.into());
         }
 
+        revoke_role(acc_auth, capacity, acc_admn.key, program_owner, role, acc_rbac.key, inp_catalog)
+    }
+
+    // Role holder removes their own role
+    pub fn renounce_role(ctx: Context<RenounceRole>,
+        _inp_root_nonce: u8,
+        inp_role: u32,
+        inp_catalog: Option<u64>,
+    ) -> anchor_lang::Result<()> {
+        ctx.accounts.root_data.require_rbac_mode(RBACMode::Slab)?;
+        ctx.accounts.root_data.require_rbac_idle()?;
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let acc_user = &ctx.accounts.rbac_user.to_account_info();
+        let role = Role::try_from_primitive(inp_role).map_err(|_| error!(ErrorCode::InvalidParameters))?;
+        let capacity = ctx.accounts.root_data.rbac_capacity;
+
         let auth_data: &mut[u8] = &mut acc_auth.try_borrow_mut_data()?;
         let rd = SlabPageAlloc::new(auth_data);
-        let authhash: u128 = CritMap::bytes_hash([[role as u32].as_byte_slice(), acc_rbac.key.as_ref()].concat().as_slice());
-
-        // Check if record exists
-        let authrec = map_get(rd, DT::UserRBAC, authhash);
-        if authrec.is_some() {
-            map_remove(rd, DT::UserRBAC, authhash).or(Err(error!(ErrorCode::InternalError)))?;
-            UserRBAC::free_index(rd, DT::UserRBAC, authrec.unwrap().slot())?;
-            msg!("Atellix: Role revoked");
-        } else {
-            msg!("Atellix: Role not found");
+        let authhash: u128 = role_hash(role, acc_user.key, inp_catalog);
+        let authrec = map_get(rd, DT::UserRBAC, capacity, authhash);
+        if authrec.is_none() {
+            msg!("Role not found");
+            return Err(ErrorCode::InvalidParameters.into());
         }
+        let slot = authrec.unwrap().slot();
+        map_remove(rd, DT::UserRBAC, capacity, authhash).or(Err(error!(ErrorCode::InternalError)))?;
+        UserRBAC::free_index(rd, DT::UserRBAC, slot)?;
+        msg!("Atellix: Role renounced");
+        emit!(RoleRenouncedEvent {
+            user: *acc_user.key,
+            role: role as u32,
+            catalog: inp_catalog,
+            slot: slot,
+        });
         Ok(())
     }
 
-    pub fn create_catalog(
-        ctx: Context<CreateCatalog>,
-        inp_catalog: u64,
+    // Grant a role as a RoleGrant account (RBACMode::Accounts), 'admin_grant' is the admin's RoleGrant unless signed by the program owner
+    pub fn grant_role_account(ctx: Context<GrantRoleAccount>,
+        _inp_root_nonce: u8,
+        inp_role: u32,
+        inp_catalog: Option<u64>,
+        inp_valid_from: i64,
+        inp_expires: i64,
     ) -> anchor_lang::Result<()> {
-        let acc_auth = &ctx.accounts.auth_data.to_account_info();
-        let acc_user = &ctx.accounts.auth_user.to_account_info();
-        let admin_role = has_role(&acc_auth, Role::CreateCatalog, acc_user.key);
-        if admin_role.is_err() {
-            msg!("No create catalog role");
+        let root_data = &ctx.accounts.root_data;
+        root_data.require_rbac_mode(RBACMode::Accounts)?;
+        let acc_rbac = &ctx.accounts.rbac_user.to_account_info();
+        let acc_admn = &ctx.accounts.program_admin.to_account_info();
+        let acc_admin_grant = &ctx.accounts.admin_grant.to_account_info();
+        let role = Role::try_from_primitive(inp_role).map_err(|_| error!(ErrorCode::InvalidParameters))?;
+
+        // Check for the admin role of the specified role
+        let admin_role = root_data.role_admin(role);
+        let mut program_owner: bool = false;
+        if admin_role.map_or(true, |r| check_role(root_data, acc_admin_grant, r, acc_admn.key, inp_catalog).is_err()) {
+            require!(is_program_owner(&ctx.accounts.program_data, root_data, acc_admn.key), ErrorCode::AccessDenied);
+            program_owner = true;
+        }
+        if root_data.requires_multisig(admin_role, program_owner) {
+            msg!("Multisig approval required");
             return Err(ErrorCode::AccessDenied.into());
         }
 
-        let root_data = &mut ctx.accounts.root_data;
-        root_data.add_catalog()?;
-        let cinst = &mut ctx.accounts.catalog;
-        cinst.catalog_id = inp_catalog;
-        cinst.catalog_counter = 0;
-        cinst.signer = ctx.accounts.catalog_signer.key();
-        cinst.manager = ctx.accounts.catalog_manager.key();
-        msg!("Atellix: Created Catalog ID: {}", cinst.catalog_id);
-        Ok(())
-    }
+        // Verify not assigning roles to self
+        if *acc_admn.key == *acc_rbac.key {
+            msg!("Cannot grant roles to self");
+            return Err(ErrorCode::AccessDenied.into());
+        }
 
-    pub fn create_url(
-        ctx: Context<CreateURL>,
-        inp_url_expand_mode: u8,
-        inp_url_hash: u128,
-        inp_url_length: u32,
-        inp_url: String,
-    ) -> anchor_lang::Result<()> {
-        let mut hasher = Shake128::default();
-        hasher.update(inp_url.as_bytes());
-        let mut reader = hasher.finalize_xof();
-        let mut hash_result = [0u8; 16];
-        reader.read(&mut hash_result);
-        let confirm_hash: u128 = u128::from_be_bytes(hash_result);
-        require!(confirm_hash == inp_url_hash, ErrorCode::InvalidURLHash); // Verifies hash used in the URL account
-        require!(inp_url.len() == inp_url_length as usize, ErrorCode::InvalidURLLength);
-        // TODO: validate expand mode
-        let url_entry = &mut ctx.accounts.url_entry;
-        url_entry.url_expand_mode = inp_url_expand_mode;
-        url_entry.url = inp_url;
+        let now = Clock::get()?.unix_timestamp;
+        check_validity(now, inp_valid_from, inp_expires)?;
+        let grant = &mut ctx.accounts.role_grant;
+        if grant.user == *acc_rbac.key && ! grant.expired(now) {
+            msg!("Atellix: Role already active");
+            emit!(RoleUnchangedEvent {
+                admin: *acc_admn.key,
+                user: *acc_rbac.key,
+                role: inp_role,
+                catalog: inp_catalog,
+                program_owner: program_owner,
+                grant: true,
+            });
+            return Ok(());
+        }
+        if grant.payer == Pubkey::default() {
+            grant.payer = acc_admn.key();
+        }
+        grant.role = inp_role;
+        grant.user = *acc_rbac.key;
+        grant.catalog = inp_catalog;
+        grant.valid_from = inp_valid_from;
+        grant.expires = inp_expires;
+        msg!("Atellix: Role granted");
+        emit!(RoleGrantedEvent {
+            admin: *acc_admn.key,
+            user: *acc_rbac.key,
+            role: inp_role,
+            catalog: inp_catalog,
+            program_owner: program_owner,
+            slot: ROLE_GRANT_SLOT,
+            valid_from: inp_valid_from,
+            expires: inp_expires,
+        });
         Ok(())
     }
 
-    pub fn create_listing(
-        ctx: Context<CreateListing>,
+    pub fn revoke_role_account(ctx: Context<RevokeRoleAccount>,
+        _inp_root_nonce: u8,
+        inp_role: u32,
+        inp_catalog: Option<u64>,
+    ) -> anchor_lang::Result<()> {
+        let root_data = &ctx.accounts.root_data;
+        root_data.require_rbac_mode(RBACMode::Accounts)?;
+        let acc_rbac = &ctx.accounts.rbac_user.to_account_info();
+        let acc_admn = &ctx.accounts.program_admin.to_account_info();
+        let acc_admin_grant = &ctx.accounts.admin_grant.to_account_info();
+        let role = Role::try_from_primitive(inp_role).map_err(|_| error!(ErrorCode::InvalidParameters))?;
+
+        // Check for the admin role of the specified role
+        let admin_role = root_data.role_admin(role);
+        let mut program_owner: bool = false;
+        if admin_role.map_or(true, |r| check_role(root_data, acc_admin_grant, r, acc_admn.key, inp_catalog).is_err()) {
+            require!(is_program_owner(&ctx.accounts.program_data, root_data, acc_admn.key), ErrorCode::AccessDenied);
+            program_owner = true;
+        }
+        if root_data.requires_multisig(admin_role, program_owner) {
+            msg!("Multisig approval required");
+            return Err(ErrorCode::AccessDenied.into());
+        }
+
+        // Account is closed to its payer
+        msg!("Atellix: Role revoked");
+        emit!(RoleRevokedEvent {
+            admin: *acc_admn.key,
+            user: *acc_rbac.key,
+            role: inp_role,
+            catalog: inp_catalog,
+            program_owner: program_owner,
+            slot: ROLE_GRANT_SLOT,
+        });
+        Ok(())
+    }
+
+    // Role holder closes their own RoleGrant account, the rent returns to the account's payer
+    pub fn renounce_role_account(ctx: Context<RenounceRoleAccount>,
+        _inp_root_nonce: u8,
+        inp_role: u32,
+        inp_catalog: Option<u64>,
+    ) -> anchor_lang::Result<()> {
+        ctx.accounts.root_data.require_rbac_mode(RBACMode::Accounts)?;
+        msg!("Atellix: Role renounced");
+        emit!(RoleRenouncedEvent {
+            user: ctx.accounts.rbac_user.key(),
+            role: inp_role,
+            catalog: inp_catalog,
+            slot: ROLE_GRANT_SLOT,
+        });
+        Ok(())
+    }
+
+    // Copy a UserRBAC grant from the slab into a RoleGrant account while in RBACMode::Migrating
+    pub fn migrate_role_account(ctx: Context<MigrateRoleAccount>,
+        _inp_root_nonce: u8,
+        inp_role: u32,
+        inp_catalog: Option<u64>,
+    ) -> anchor_lang::Result<()> {
+        ctx.accounts.root_data.require_rbac_mode(RBACMode::Migrating)?;
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let acc_rbac = &ctx.accounts.rbac_user.to_account_info();
+        let role = Role::try_from_primitive(inp_role).map_err(|_| error!(ErrorCode::InvalidParameters))?;
+        let capacity = ctx.accounts.root_data.rbac_capacity;
+
+        let auth_data: &mut[u8] = &mut acc_auth.try_borrow_mut_data()?;
+        let rd = SlabPageAlloc::new(auth_data);
+        let authrec = map_get(rd, DT::UserRBAC, capacity, role_hash(role, acc_rbac.key, inp_catalog));
+        if authrec.is_none() {
+            msg!("Role not found");
+            return Err(ErrorCode::InvalidParameters.into());
+        }
+        let urec = rd.index::<UserRBAC>(DT::UserRBAC as u16, authrec.unwrap().slot() as usize);
+        let grant = &mut ctx.accounts.role_grant;
+        grant.role = inp_role;
+        grant.user = *acc_rbac.key;
+        grant.catalog = urec.catalog();
+        grant.valid_from = urec.valid_from;
+        grant.expires = urec.expires;
+        grant.payer = ctx.accounts.program_admin.key();
+        let root_data = &mut ctx.accounts.root_data;
+        root_data.rbac_migrated = root_data.rbac_migrated.checked_add(1).ok_or(error!(ErrorCode::Overflow))?;
+        msg!("Atellix: Role migrated");
+        Ok(())
+    }
+
+    // Switch role storage: Slab -> Migrating -> Accounts, Accounts requires every slab grant to be migrated with migrate_role_account
+    // Switching back from Accounts is not supported (RoleGrant accounts would be ignored), Migrating can only be cancelled before any grant is migrated
+    pub fn set_rbac_mode(ctx: Context<UpdateRBACMode>,
+        inp_mode: u8,
+    ) -> anchor_lang::Result<()> {
+        let mode = RBACMode::try_from_primitive(inp_mode).map_err(|_| error!(ErrorCode::InvalidParameters))?;
+        let root_data = &mut ctx.accounts.root_data;
+        match (root_data.rbac_mode()?, mode) {
+            (RBACMode::Slab, RBACMode::Migrating) => {
+                root_data.require_rbac_mode(RBACMode::Slab)?;
+                root_data.require_rbac_idle()?;
+                root_data.rbac_migrated = 0;
+            },
+            (RBACMode::Migrating, RBACMode::Slab) => {
+                if root_data.rbac_migrated != 0 {
+                    msg!("Grants already migrated");
+                    return Err(ErrorCode::InvalidRBACMode.into());
+                }
+            },
+            (RBACMode::Migrating, RBACMode::Accounts) => {
+                let acc_auth = &ctx.accounts.auth_data.to_account_info();
+                let auth_data: &mut[u8] = &mut acc_auth.try_borrow_mut_data()?;
+                let rd = SlabPageAlloc::new(auth_data);
+                let total = count_roles(rd, root_data.rbac_capacity);
+                if root_data.rbac_migrated != total {
+                    msg!("Migrated: {} Grants: {}", root_data.rbac_migrated, total);
+                    return Err(ErrorCode::InvalidRBACMode.into());
+                }
+            },
+            _ => {
+                msg!("Invalid RBAC mode change");
+                return Err(ErrorCode::InvalidRBACMode.into());
+            },
+        }
+        root_data.rbac_mode = inp_mode;
+        msg!("Atellix: RBAC mode: {}", inp_mode);
+        Ok(())
+    }
+
+    // Set the role allowed to grant and revoke 'inp_role' (None = program owner only), needs a proposal once the threshold is above 1
+    pub fn set_role_admin(ctx: Context<UpdateRootConfig>,
+        inp_role: u32,
+        inp_admin_role: Option<u32>,
+    ) -> anchor_lang::Result<()> {
+        if ctx.accounts.root_data.admin_threshold > 1 {
+            msg!("Multisig approval required");
+            return Err(ErrorCode::AccessDenied.into());
+        }
+        let role = Role::try_from_primitive(inp_role).map_err(|_| error!(ErrorCode::InvalidParameters))?;
+        if role == Role::NetworkAdmin {
+            msg!("Reserved for program owner");
+            return Err(ErrorCode::AccessDenied.into());
+        }
+        let admin_role = match inp_admin_role {
+            Some(r) => Role::try_from_primitive(r).map_err(|_| error!(ErrorCode::InvalidParameters))? as u32,
+            None => ROLE_ADMIN_OWNER,
+        };
+        ctx.accounts.root_data.role_admin[role as usize] = admin_role;
+        msg!("Atellix: Role admin updated");
+        Ok(())
+    }
+
+    pub fn set_admin_threshold(ctx: Context<UpdateRootConfig>,
+        inp_threshold: u32,
+    ) -> anchor_lang::Result<()> {
+        if inp_threshold as usize > MAX_APPROVALS {
+            msg!("Threshold exceeds maximum approvals");
+            return Err(ErrorCode::InvalidParameters.into());
+        }
+        let root_data = &ctx.accounts.root_data;
+        if root_data.admin_threshold > 1 && inp_threshold < root_data.admin_threshold {
+            msg!("Multisig approval required");
+            return Err(ErrorCode::AccessDenied.into());
+        }
+        ctx.accounts.root_data.admin_threshold = inp_threshold;
+        msg!("Atellix: Admin threshold: {}", inp_threshold);
+        Ok(())
+    }
+
+    // Propose a grant, revoke, threshold change or role admin change to be approved by holders of the role's admin role (or the program owner),
+    // the proposer is the first approver, 'inp_threshold' is the new threshold or new admin role (ROLE_ADMIN_OWNER = program owner only)
+    pub fn propose_admin_action(ctx: Context<ProposeAdminAction>,
+        inp_proposal_id: u64,
+        inp_action: u8,
+        inp_role: u32,
+        inp_catalog: Option<u64>,
+        inp_valid_from: i64,
+        inp_expires: i64,
+        inp_deadline: i64,
+        inp_threshold: u32,
+    ) -> anchor_lang::Result<()> {
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let acc_prop = &ctx.accounts.proposer.to_account_info();
+        let acc_rbac = &ctx.accounts.rbac_user.to_account_info();
+        let action = ProposalAction::try_from_primitive(inp_action).map_err(|_| error!(ErrorCode::InvalidParameters))?;
+        let role = Role::try_from_primitive(inp_role).map_err(|_| error!(ErrorCode::InvalidParameters))?;
+        let admin_role = proposal_admin_role(&ctx.accounts.root_data, action, role);
+        if ! is_admin_approver(&ctx.accounts.root_data, &ctx.accounts.program_data, std::slice::from_ref(acc_auth), admin_role, acc_prop.key, inp_catalog) {
+            msg!("No admin role");
+            return Err(ErrorCode::AccessDenied.into());
+        }
+        if action == ProposalAction::SetThreshold {
+            require!(inp_threshold as usize <= MAX_APPROVALS && inp_catalog.is_none(), ErrorCode::InvalidParameters);
+        } else if action == ProposalAction::SetRoleAdmin {
+            if role == Role::NetworkAdmin {
+                msg!("Reserved for program owner");
+                return Err(ErrorCode::AccessDenied.into());
+            }
+            let valid_admin = inp_threshold == ROLE_ADMIN_OWNER || Role::try_from_primitive(inp_threshold).is_ok();
+            require!(valid_admin && inp_catalog.is_none(), ErrorCode::InvalidParameters);
+        } else if *acc_prop.key == *acc_rbac.key {
+            msg!("Cannot propose roles for self");
+            return Err(ErrorCode::AccessDenied.into());
+        }
+        let clock = Clock::get()?;
+        require!(inp_deadline > clock.unix_timestamp, ErrorCode::InvalidParameters);
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.proposal_id = inp_proposal_id;
+        proposal.proposer = acc_prop.key();
+        proposal.action = inp_action;
+        proposal.role = inp_role;
+        proposal.user = acc_rbac.key();
+        proposal.catalog = inp_catalog;
+        proposal.valid_from = inp_valid_from;
+        proposal.expires = inp_expires;
+        proposal.deadline = inp_deadline;
+        proposal.threshold = inp_threshold;
+        proposal.approvals = vec![acc_prop.key()];
+        emit!(ProposalEvent {
+            proposal: proposal.key(),
+            proposal_id: inp_proposal_id,
+            admin: acc_prop.key(),
+            status: ProposalStatus::Proposed as u8,
+            approvals: 1,
+        });
+        Ok(())
+    }
+
+    pub fn approve_admin_action(ctx: Context<ApproveAdminAction>,
+        _inp_proposal_id: u64,
+    ) -> anchor_lang::Result<()> {
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let acc_appr = &ctx.accounts.approver.to_account_info();
+        let proposal = &mut ctx.accounts.proposal;
+        let admin_role = proposal.admin_role(&ctx.accounts.root_data)?;
+        if ! is_admin_approver(&ctx.accounts.root_data, &ctx.accounts.program_data, std::slice::from_ref(acc_auth), admin_role, acc_appr.key, proposal.catalog) {
+            msg!("No admin role");
+            return Err(ErrorCode::AccessDenied.into());
+        }
+        require!(Clock::get()?.unix_timestamp < proposal.deadline, ErrorCode::ProposalExpired);
+        if proposal.approvals.contains(acc_appr.key) {
+            msg!("Already approved");
+            return Err(ErrorCode::InvalidParameters.into());
+        }
+        require!(proposal.approvals.len() < MAX_APPROVALS, ErrorCode::InvalidParameters);
+        proposal.approvals.push(acc_appr.key());
+        emit!(ProposalEvent {
+            proposal: proposal.key(),
+            proposal_id: proposal.proposal_id,
+            admin: acc_appr.key(),
+            status: ProposalStatus::Approved as u8,
+            approvals: proposal.approvals.len() as u32,
+        });
+        Ok(())
+    }
+
+    // Permissionless once the approval threshold is met, approvers must still be the program owner or hold the admin role
+    // In RBACMode::Accounts the approvers' RoleGrant accounts are passed as remaining accounts
+    pub fn execute_admin_action<'info>(ctx: Context<'_, '_, '_, 'info, ExecuteAdminAction<'info>>,
+        _inp_proposal_id: u64,
+    ) -> anchor_lang::Result<()> {
+        let mode = ctx.accounts.root_data.rbac_mode()?;
+        if mode != RBACMode::Accounts {
+            ctx.accounts.root_data.require_rbac_mode(RBACMode::Slab)?;
+            ctx.accounts.root_data.require_rbac_idle()?;
+        }
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let acc_grant = &ctx.accounts.role_grant.to_account_info();
+        let acc_auths: Vec<AccountInfo> = match mode {
+            RBACMode::Accounts => ctx.remaining_accounts.to_vec(),
+            _ => vec![acc_auth.clone()],
+        };
+        let capacity = ctx.accounts.root_data.rbac_capacity;
+        let threshold = ctx.accounts.root_data.admin_threshold.max(1);
+        let proposal = &ctx.accounts.proposal;
+        require!(Clock::get()?.unix_timestamp < proposal.deadline, ErrorCode::ProposalExpired);
+        let role = Role::try_from_primitive(proposal.role).map_err(|_| error!(ErrorCode::InvalidParameters))?;
+        let admin_role = proposal.admin_role(&ctx.accounts.root_data)?;
+        let mut approvals: u32 = 0;
+        for approver in proposal.approvals.iter() {
+            if is_admin_approver(&ctx.accounts.root_data, &ctx.accounts.program_data, &acc_auths, admin_role, approver, proposal.catalog) {
+                approvals = approvals.checked_add(1).ok_or(error!(ErrorCode::Overflow))?;
+            }
+        }
+        if approvals < threshold {
+            msg!("Approvals: {} Required: {}", approvals, threshold);
+            return Err(ErrorCode::AccessDenied.into());
+        }
+        let action = ProposalAction::try_from_primitive(proposal.action).map_err(|_| error!(ErrorCode::InvalidParameters))?;
+        let entry = RoleEntry {
+            user: proposal.user,
+            role: proposal.role,
+            catalog: proposal.catalog,
+            valid_from: proposal.valid_from,
+            expires: proposal.expires,
+        };
+        let acc_payer = &ctx.accounts.payer.to_account_info();
+        match (action, mode) {
+            (ProposalAction::Grant, RBACMode::Accounts) => {
+                grant_role_account_data(acc_grant, acc_payer, &ctx.accounts.system_program.to_account_info(), &proposal.proposer, false, &entry)?;
+            },
+            (ProposalAction::Grant, _) => {
+                grant_role(acc_auth, capacity, &proposal.proposer, false, &entry)?;
+            },
+            (ProposalAction::Revoke, RBACMode::Accounts) => {
+                revoke_role_account_data(acc_grant, &ctx.accounts.grant_payer.to_account_info(), &proposal.proposer, false, role, &proposal.user, proposal.catalog)?;
+            },
+            (ProposalAction::Revoke, _) => {
+                revoke_role(acc_auth, capacity, &proposal.proposer, false, role, &proposal.user, proposal.catalog)?;
+            },
+            (ProposalAction::SetThreshold, _) => {
+                msg!("Atellix: Admin threshold: {}", proposal.threshold);
+            },
+            (ProposalAction::SetRoleAdmin, _) => {
+                msg!("Atellix: Role admin updated");
+            },
+        }
+        emit!(ProposalEvent {
+            proposal: proposal.key(),
+            proposal_id: proposal.proposal_id,
+            admin: proposal.proposer,
+            status: ProposalStatus::Executed as u8,
+            approvals: approvals,
+        });
+        if action == ProposalAction::SetThreshold {
+            let new_threshold = proposal.threshold;
+            ctx.accounts.root_data.admin_threshold = new_threshold;
+        } else if action == ProposalAction::SetRoleAdmin {
+            let new_admin = proposal.threshold;
+            ctx.accounts.root_data.role_admin[role as usize] = new_admin;
+        }
+        Ok(())
+    }
+
+    // The proposer can cancel at any time, anyone can close a proposal after its deadline
+    pub fn cancel_admin_action(ctx: Context<CancelAdminAction>,
+        _inp_proposal_id: u64,
+    ) -> anchor_lang::Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        let user = ctx.accounts.user.key();
+        if user != proposal.proposer && Clock::get()?.unix_timestamp < proposal.deadline {
+            msg!("Proposal has not expired");
+            return Err(ErrorCode::AccessDenied.into());
+        }
+        emit!(ProposalEvent {
+            proposal: proposal.key(),
+            proposal_id: proposal.proposal_id,
+            admin: user,
+            status: ProposalStatus::Cancelled as u8,
+            approvals: proposal.approvals.len() as u32,
+        });
+        Ok(())
+    }
+
+    // Permissionless cleanup of expired grants
+    pub fn prune_expired_roles(ctx: Context<PruneRBAC>,
+        _inp_root_nonce: u8,
+        inp_role: u32,
+        inp_catalog: Option<u64>,
+        inp_users: Vec<Pubkey>,
+    ) -> anchor_lang::Result<()> {
+        ctx.accounts.root_data.require_rbac_mode(RBACMode::Slab)?;
+        ctx.accounts.root_data.require_rbac_idle()?;
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let role = Role::try_from_primitive(inp_role).map_err(|_| error!(ErrorCode::InvalidParameters))?;
+        let now = Clock::get()?.unix_timestamp;
+        let capacity = ctx.accounts.root_data.rbac_capacity;
+
+        let auth_data: &mut[u8] = &mut acc_auth.try_borrow_mut_data()?;
+        let rd = SlabPageAlloc::new(auth_data);
+        let mut pruned: u32 = 0;
+        for user in inp_users.iter() {
+            let authhash: u128 = role_hash(role, user, inp_catalog);
+            let authrec = map_get(rd, DT::UserRBAC, capacity, authhash);
+            if authrec.is_none() {
+                continue;
+            }
+            let slot = authrec.unwrap().slot();
+            if ! rd.index::<UserRBAC>(DT::UserRBAC as u16, slot as usize).expired(now) {
+                continue;
+            }
+            map_remove(rd, DT::UserRBAC, capacity, authhash).or(Err(error!(ErrorCode::InternalError)))?;
+            UserRBAC::free_index(rd, DT::UserRBAC, slot)?;
+            pruned = pruned.checked_add(1).ok_or(error!(ErrorCode::Overflow))?;
+        }
+        msg!("Atellix: Pruned {} expired roles", pruned);
+        Ok(())
+    }
+
+    // Permissionless cleanup of expired RoleGrant accounts, remaining accounts are (role_grant, payer) pairs and rent is returned to the payer
+    pub fn prune_role_accounts(ctx: Context<PruneRoleAccounts>) -> anchor_lang::Result<()> {
+        ctx.accounts.root_data.require_rbac_mode(RBACMode::Accounts)?;
+        let now = Clock::get()?.unix_timestamp;
+        let mut pruned: u32 = 0;
+        for pair in ctx.remaining_accounts.chunks(2) {
+            require!(pair.len() == 2, ErrorCode::InvalidParameters);
+            let (acc_grant, acc_payer) = (&pair[0], &pair[1]);
+            if *acc_grant.owner != crate::ID {
+                continue;
+            }
+            let grant = {
+                let grant_data: &[u8] = &acc_grant.try_borrow_data()?;
+                RoleGrant::try_deserialize(&mut &grant_data[..])
+            };
+            let grant = match grant {
+                Ok(g) => g,
+                Err(_) => continue,
+            };
+            if ! grant.expired(now) {
+                continue;
+            }
+            require!(grant.payer == *acc_payer.key, ErrorCode::InvalidParameters);
+            close_account(acc_grant, acc_payer)?;
+            pruned = pruned.checked_add(1).ok_or(error!(ErrorCode::Overflow))?;
+        }
+        msg!("Atellix: Pruned {} expired roles", pruned);
+        Ok(())
+    }
+
+    // Start moving all grants into a larger RBAC account with copy_rbac_page, the current account is closed when the copy completes
+    // Slab elements in the original UserRBACV0 layout are converted
+    pub fn migrate_rbac(ctx: Context<UpdateRootAuthority>,
+        inp_capacity: u32,
+    ) -> anchor_lang::Result<()> {
+        let acc_new_auth = &ctx.accounts.new_auth_data.to_account_info();
+        let root_data = &mut ctx.accounts.root_data;
+        // Slab mode in any version, legacy slabs are converted by the copy
+        if root_data.rbac_mode()? != RBACMode::Slab {
+            msg!("Not available in the current RBAC mode");
+            return Err(ErrorCode::InvalidRBACMode.into());
+        }
+        root_data.require_rbac_idle()?;
+        if inp_capacity < root_data.rbac_capacity {
+            msg!("Capacity cannot be reduced");
+            return Err(ErrorCode::InvalidParameters.into());
+        }
+        setup_rbac(acc_new_auth, inp_capacity)?;
+        root_data.start_rbac_copy(acc_new_auth.key(), inp_capacity, true);
+        msg!("Atellix: RBAC copy started");
+        Ok(())
+    }
+
+    // Point RootData at a new RBAC account, optionally copying existing grants with copy_rbac_page (the previous account is left as-is)
+    pub fn set_root_authority(ctx: Context<UpdateRootAuthority>,
+        inp_capacity: u32,
+        inp_copy_grants: bool,
+    ) -> anchor_lang::Result<()> {
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let acc_new_auth = &ctx.accounts.new_auth_data.to_account_info();
+        let root_data = &mut ctx.accounts.root_data;
+        if root_data.rbac_mode()? != RBACMode::Slab {
+            msg!("Not available in the current RBAC mode");
+            return Err(ErrorCode::InvalidRBACMode.into());
+        }
+        root_data.require_rbac_idle()?;
+        if inp_copy_grants {
+            let auth_data: &mut[u8] = &mut acc_auth.try_borrow_mut_data()?;
+            let total = count_roles(SlabPageAlloc::new(auth_data), root_data.rbac_capacity);
+            if inp_capacity < total {
+                msg!("Capacity below current grants: {}", total);
+                return Err(ErrorCode::InvalidParameters.into());
+            }
+        }
+        setup_rbac(acc_new_auth, inp_capacity)?;
+        if inp_copy_grants {
+            root_data.start_rbac_copy(acc_new_auth.key(), inp_capacity, false);
+            msg!("Atellix: RBAC copy started");
+            return Ok(());
+        }
+        root_data.set_root_authority(acc_new_auth.key());
+        root_data.rbac_capacity = inp_capacity;
+        root_data.rbac_version = RBAC_VERSION;
+        msg!("Atellix: Root authority updated");
+        emit!(RootAuthorityUpdatedEvent {
+            admin: ctx.accounts.program_admin.key(),
+            prev_authority: acc_auth.key(),
+            new_authority: acc_new_auth.key(),
+            rbac_capacity: inp_capacity,
+            roles_copied: None,
+        });
+        Ok(())
+    }
+
+    // Abandon a copy started by migrate_rbac or set_root_authority, the current RBAC account stays in use
+    pub fn cancel_rbac_copy(ctx: Context<UpdateRootConfig>) -> anchor_lang::Result<()> {
+        let root_data = &mut ctx.accounts.root_data;
+        if root_data.pending_authority == Pubkey::default() {
+            msg!("No RBAC copy in progress");
+            return Err(ErrorCode::InvalidParameters.into());
+        }
+        root_data.start_rbac_copy(Pubkey::default(), 0, false);
+        msg!("Atellix: RBAC copy cancelled");
+        Ok(())
+    }
+
+    // Copy the next page of grants started by migrate_rbac or set_root_authority, the new account takes effect after the last page
+    pub fn copy_rbac_page(ctx: Context<CopyRBAC>,
+        inp_limit: u32,
+    ) -> anchor_lang::Result<()> {
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let acc_new_auth = &ctx.accounts.new_auth_data.to_account_info();
+        let root_data = &mut ctx.accounts.root_data;
+        if root_data.pending_authority == Pubkey::default() {
+            msg!("No RBAC copy in progress");
+            return Err(ErrorCode::InvalidParameters.into());
+        }
+        let (cursor, copied) = copy_rbac(acc_auth, root_data.rbac_capacity, root_data.rbac_version,
+            acc_new_auth, root_data.pending_capacity, root_data.copy_cursor, inp_limit)?;
+        root_data.copy_cursor = cursor;
+        root_data.copy_count = root_data.copy_count.checked_add(copied).ok_or(error!(ErrorCode::Overflow))?;
+        msg!("Atellix: Copied {} roles", copied);
+        if cursor < root_data.rbac_capacity {
+            return Ok(());
+        }
+        if root_data.copy_close {
+            close_account(acc_auth, &ctx.accounts.program_admin.to_account_info())?;
+        }
+        let roles_copied = root_data.copy_count;
+        root_data.set_root_authority(acc_new_auth.key());
+        root_data.rbac_capacity = root_data.pending_capacity;
+        root_data.rbac_version = RBAC_VERSION;
+        root_data.start_rbac_copy(Pubkey::default(), 0, false);
+        msg!("Atellix: Root authority updated");
+        emit!(RootAuthorityUpdatedEvent {
+            admin: ctx.accounts.program_admin.key(),
+            prev_authority: acc_auth.key(),
+            new_authority: acc_new_auth.key(),
+            rbac_capacity: root_data.rbac_capacity,
+            roles_copied: Some(roles_copied),
+        });
+        Ok(())
+    }
+
+    // Convert RootData from the original layout (catalog_count, root_authority), the RBAC account is converted by migrate_rbac
+    pub fn migrate_root_data(ctx: Context<MigrateRootData>) -> anchor_lang::Result<()> {
+        let acc_root = &ctx.accounts.root_data.to_account_info();
+        require!(*acc_root.owner == crate::ID, ErrorCode::InternalError);
+        let (catalog_count, root_authority) = {
+            let root_data: &[u8] = &acc_root.try_borrow_data()?;
+            if root_data.len() != 48 || root_data[..8] != RootData::discriminator() {
+                msg!("RootData already migrated");
+                return Err(ErrorCode::InvalidParameters.into());
+            }
+            let mut legacy: &[u8] = &root_data[8..];
+            let catalog_count = u64::deserialize(&mut legacy)?;
+            let root_authority = Pubkey::deserialize(&mut legacy)?;
+            (catalog_count, root_authority)
+        };
+        let mut rt = RootData {
+            catalog_count: catalog_count,
+            root_authority: root_authority,
+            rbac_capacity: MAX_RBAC,
+            admin_threshold: 0,
+            super_admin: ctx.accounts.program_admin.key(),
+            role_admin: [Role::NetworkAdmin as u32; MAX_ROLES],
+            rbac_mode: RBACMode::Slab as u8,
+            rbac_version: 0,
+            ..Default::default()
+        };
+        rt.role_admin[Role::NetworkAdmin as usize] = ROLE_ADMIN_OWNER;
+        realloc_account(acc_root, &ctx.accounts.program_admin.to_account_info(), &ctx.accounts.system_program.to_account_info(), 236)?;
+        let root_data: &mut [u8] = &mut acc_root.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = root_data;
+        rt.try_serialize(&mut writer)?;
+        msg!("Atellix: Migrated RootData");
+        Ok(())
+    }
+
+    // View instruction for simulateTransaction, returns a borsh-encoded RoleList
+    // In RBACMode::Accounts the slab is no longer updated, RoleGrant accounts are listed with getProgramAccounts instead
+    pub fn list_roles(ctx: Context<ListRoles>,
+        inp_cursor: u32,
+        inp_limit: u32,
+    ) -> anchor_lang::Result<()> {
+        if ctx.accounts.root_data.rbac_mode()? == RBACMode::Accounts {
+            msg!("Grants are stored as RoleGrant accounts");
+            return Err(ErrorCode::InvalidRBACMode.into());
+        }
+        ctx.accounts.root_data.require_rbac_version()?;
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let auth_data: &mut[u8] = &mut acc_auth.try_borrow_mut_data()?;
+        let rd = SlabPageAlloc::new(auth_data);
+        let page = role_page(rd, ctx.accounts.root_data.rbac_capacity, inp_cursor, inp_limit.min(MAX_ROLE_PAGE));
+        set_return_data(&page.try_to_vec()?);
+        Ok(())
+    }
+
+    // Set (inp_pause = true) or clear network-wide pause flags
+    pub fn set_network_pause(ctx: Context<UpdateNetworkPause>,
+        _inp_root_nonce: u8,
+        inp_flags: u8,
+        inp_pause: bool,
+    ) -> anchor_lang::Result<()> {
+        require!(inp_flags != 0 && inp_flags & !PAUSE_ALL == 0, ErrorCode::InvalidParameters);
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let acc_user = &ctx.accounts.auth_user.to_account_info();
+        let pauser_role = check_role(&ctx.accounts.root_data, &acc_auth, Role::Pauser, acc_user.key, None);
+        if pauser_role.is_err() {
+            require!(is_program_owner(&ctx.accounts.program_data, &ctx.accounts.root_data, acc_user.key), ErrorCode::AccessDenied);
+        }
+        let root_data = &mut ctx.accounts.root_data;
+        if inp_pause {
+            root_data.paused = root_data.paused | inp_flags;
+        } else {
+            root_data.paused = root_data.paused & !inp_flags;
+        }
+        emit_pause(acc_user.key, None, inp_flags, root_data.paused, inp_pause);
+        Ok(())
+    }
+
+    // Set (inp_pause = true) or clear pause flags for a single catalog
+    pub fn set_catalog_pause(ctx: Context<UpdateCatalogPause>,
+        _inp_root_nonce: u8,
+        inp_flags: u8,
+        inp_pause: bool,
+    ) -> anchor_lang::Result<()> {
+        require!(inp_flags != 0 && inp_flags & !PAUSE_ALL == 0, ErrorCode::InvalidParameters);
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let acc_user = &ctx.accounts.auth_user.to_account_info();
+        let catalog = &mut ctx.accounts.catalog;
+        let pauser_role = check_role(&ctx.accounts.root_data, &acc_auth, Role::Pauser, acc_user.key, Some(catalog.catalog_id));
+        if pauser_role.is_err() {
+            require!(is_program_owner(&ctx.accounts.program_data, &ctx.accounts.root_data, acc_user.key), ErrorCode::AccessDenied);
+        }
+        if inp_pause {
+            catalog.paused = catalog.paused | inp_flags;
+        } else {
+            catalog.paused = catalog.paused & !inp_flags;
+        }
+        emit_pause(acc_user.key, Some(catalog.catalog_id), inp_flags, catalog.paused, inp_pause);
+        Ok(())
+    }
+
+    pub fn create_catalog(
+        ctx: Context<CreateCatalog>,
+        inp_catalog: u64,
+        inp_name_hash: u128,
+        inp_name: String,
+        inp_schema_version: u32,
+    ) -> anchor_lang::Result<()> {
+        check_pause(&ctx.accounts.root_data, None, PAUSE_CREATE)?;
+        require!(inp_name.len() > 0 && inp_name.len() <= MAX_CATALOG_NAME, ErrorCode::InvalidParameters);
+        require!(shake128_hash(inp_name.as_bytes()) == inp_name_hash, ErrorCode::InvalidParameters); // Verifies hash used in the name account
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let acc_user = &ctx.accounts.auth_user.to_account_info();
+        let admin_role = check_role(&ctx.accounts.root_data, &acc_auth, Role::CreateCatalog, acc_user.key, Some(inp_catalog));
+        if admin_role.is_err() {
+            msg!("No create catalog role");
+            return Err(ErrorCode::AccessDenied.into());
+        }
+
+        let root_data = &mut ctx.accounts.root_data;
+        root_data.add_catalog()?;
+        let cinst = &mut ctx.accounts.catalog;
+        cinst.catalog_id = inp_catalog;
+        cinst.catalog_counter = 0;
+        cinst.listing_count = 0;
+        cinst.signers = [CatalogSigner::default(); MAX_CATALOG_SIGNERS];
+        cinst.signers[0].key = ctx.accounts.catalog_signer.key();
+        cinst.manager = ctx.accounts.catalog_manager.key();
+        cinst.paused = 0;
+        cinst.name = inp_name;
+        cinst.description_url = ctx.accounts.description_url.key();
+        cinst.schema_version = inp_schema_version;
+        cinst.created_ts = Clock::get()?.unix_timestamp;
+        cinst.fee_mint = Pubkey::default();
+        cinst.fee_vault = Pubkey::default(); // No fee policy
+        cinst.version = CATALOG_VERSION;
+        update_url_refs(&ctx.accounts.description_url.to_account_info(), true)?;
+        let cname = &mut ctx.accounts.catalog_name;
+        cname.catalog_id = inp_catalog;
+        cname.catalog = cinst.key();
+        msg!("Atellix: Created Catalog ID: {}", cinst.catalog_id);
+        Ok(())
+    }
+
+    // Convert a catalog from the original layout (catalog_id, catalog_counter, signer, manager)
+    // The number of live listings is not stored in the original layout, creates and removals should be paused while it is counted
+    pub fn migrate_catalog(
+        ctx: Context<MigrateCatalog>,
+        _inp_catalog: u64,
+        inp_listing_count: u64,
+        inp_name_hash: u128,
+        inp_name: String,
+        inp_schema_version: u32,
+    ) -> anchor_lang::Result<()> {
+        require!(inp_name.len() > 0 && inp_name.len() <= MAX_CATALOG_NAME, ErrorCode::InvalidParameters);
+        require!(shake128_hash(inp_name.as_bytes()) == inp_name_hash, ErrorCode::InvalidParameters); // Verifies hash used in the name account
+        let acc_cat = &ctx.accounts.catalog.to_account_info();
+        require!(*acc_cat.owner == crate::ID, ErrorCode::InvalidParameters);
+        let (catalog_id, catalog_counter, signer, manager) = {
+            let cat_data: &[u8] = &acc_cat.try_borrow_data()?;
+            if cat_data.len() != 88 || cat_data[..8] != CatalogInstance::discriminator() {
+                msg!("Catalog already migrated");
+                return Err(ErrorCode::InvalidParameters.into());
+            }
+            let mut legacy: &[u8] = &cat_data[8..];
+            let catalog_id = u64::deserialize(&mut legacy)?;
+            let catalog_counter = u64::deserialize(&mut legacy)?;
+            let signer = Pubkey::deserialize(&mut legacy)?;
+            let manager = Pubkey::deserialize(&mut legacy)?;
+            (catalog_id, catalog_counter, signer, manager)
+        };
+        require!(inp_listing_count <= catalog_counter, ErrorCode::InvalidParameters);
+        let mut cinst = CatalogInstance {
+            catalog_id: catalog_id,
+            catalog_counter: catalog_counter,
+            listing_count: inp_listing_count,
+            manager: manager,
+            name: inp_name,
+            description_url: Pubkey::default(),   // No description URL
+            schema_version: inp_schema_version,
+            created_ts: 0,                         // Unknown
+            fee_mint: Pubkey::default(),
+            fee_vault: Pubkey::default(),          // No fee policy
+            version: CATALOG_VERSION,
+            ..Default::default()
+        };
+        cinst.signers[0].key = signer;
+        realloc_account(acc_cat, &ctx.accounts.program_admin.to_account_info(), &ctx.accounts.system_program.to_account_info(), 578)?;
+        {
+            let cat_data: &mut [u8] = &mut acc_cat.try_borrow_mut_data()?;
+            let mut writer: &mut [u8] = cat_data;
+            cinst.try_serialize(&mut writer)?;
+        }
+        let cname = &mut ctx.accounts.catalog_name;
+        cname.catalog_id = catalog_id;
+        cname.catalog = acc_cat.key();
+        msg!("Atellix: Migrated Catalog ID: {}", catalog_id);
+        Ok(())
+    }
+
+    // Correct the live listing count of a migrated catalog if the count given to migrate_catalog was wrong
+    pub fn set_listing_count(
+        ctx: Context<SetListingCount>,
+        _inp_catalog: u64,
+        inp_listing_count: u64,
+    ) -> anchor_lang::Result<()> {
+        let catalog = &mut ctx.accounts.catalog;
+        require!(inp_listing_count <= catalog.catalog_counter, ErrorCode::InvalidParameters);
+        catalog.listing_count = inp_listing_count;
+        msg!("Atellix: Catalog ID: {} Listing count: {}", catalog.catalog_id, inp_listing_count);
+        Ok(())
+    }
+
+    // Replace the catalog signers and / or rotate the manager (None = unchanged)
+    pub fn update_catalog(
+        ctx: Context<UpdateCatalog>,
+        inp_signers: Option<Vec<CatalogSigner>>,
+        inp_manager: Option<Pubkey>,
+    ) -> anchor_lang::Result<()> {
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let acc_user = &ctx.accounts.auth_user.to_account_info();
+        let catalog = &mut ctx.accounts.catalog;
+        if catalog.manager != *acc_user.key {
+            let admin_role = check_role(&ctx.accounts.root_data, &acc_auth, Role::CreateCatalog, acc_user.key, Some(catalog.catalog_id));
+            if admin_role.is_err() {
+                msg!("Not catalog manager or create catalog role");
+                return Err(ErrorCode::AccessDenied.into());
+            }
+        }
+        require!(inp_signers.is_some() || inp_manager.is_some(), ErrorCode::InvalidParameters);
+        let prev_signers = catalog.signers;
+        let prev_manager = catalog.manager;
+        if let Some(signers) = inp_signers {
+            require!(signers.len() <= MAX_CATALOG_SIGNERS, ErrorCode::InvalidParameters);
+            for signer in signers.iter() {
+                if signer.not_after != 0 && signer.not_after <= signer.not_before {
+                    msg!("Invalid signer validity period");
+                    return Err(ErrorCode::InvalidParameters.into());
+                }
+            }
+            catalog.signers = [CatalogSigner::default(); MAX_CATALOG_SIGNERS];
+            catalog.signers[..signers.len()].copy_from_slice(&signers);
+        }
+        if let Some(manager) = inp_manager {
+            catalog.manager = manager;
+        }
+        msg!("Atellix: Updated Catalog ID: {}", catalog.catalog_id);
+        emit!(CatalogUpdatedEvent {
+            user: *acc_user.key,
+            catalog: catalog.catalog_id,
+            prev_signers: prev_signers,
+            signers: catalog.signers,
+            prev_manager: prev_manager,
+            manager: catalog.manager,
+        });
+        Ok(())
+    }
+
+    // Restrict listing fees to a vault, fee range and exempt owners (fee_max 0 = no maximum)
+    pub fn set_fee_policy(
+        ctx: Context<UpdateFeePolicy>,
+        inp_fee_min: u64,
+        inp_fee_max: u64,
+        inp_fee_exempt: Vec<Pubkey>,
+    ) -> anchor_lang::Result<()> {
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let acc_user = &ctx.accounts.auth_user.to_account_info();
+        let catalog = &mut ctx.accounts.catalog;
+        if catalog.manager != *acc_user.key {
+            let admin_role = check_role(&ctx.accounts.root_data, &acc_auth, Role::CreateCatalog, acc_user.key, Some(catalog.catalog_id));
+            if admin_role.is_err() {
+                msg!("Not catalog manager or create catalog role");
+                return Err(ErrorCode::AccessDenied.into());
+            }
+        }
+        require!(inp_fee_max == 0 || inp_fee_max >= inp_fee_min, ErrorCode::InvalidParameters);
+        require!(inp_fee_exempt.len() <= MAX_FEE_EXEMPT, ErrorCode::InvalidParameters);
+        catalog.fee_mint = ctx.accounts.fee_mint.key();
+        catalog.fee_vault = ctx.accounts.fee_vault.key();
+        catalog.fee_min = inp_fee_min;
+        catalog.fee_max = inp_fee_max;
+        catalog.fee_exempt = [Pubkey::default(); MAX_FEE_EXEMPT];
+        catalog.fee_exempt[..inp_fee_exempt.len()].copy_from_slice(&inp_fee_exempt);
+        msg!("Atellix: Updated fee policy for Catalog ID: {}", catalog.catalog_id);
+        emit!(FeePolicyUpdatedEvent {
+            user: *acc_user.key,
+            catalog: catalog.catalog_id,
+            fee_mint: catalog.fee_mint,
+            fee_vault: catalog.fee_vault,
+            fee_min: catalog.fee_min,
+            fee_max: catalog.fee_max,
+            fee_exempt: inp_fee_exempt,
+        });
+        Ok(())
+    }
+
+    // Remove the fee policy, listings can then be created without fees or with fees in any mint
+    pub fn clear_fee_policy(
+        ctx: Context<UpdateCatalog>,
+    ) -> anchor_lang::Result<()> {
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let acc_user = &ctx.accounts.auth_user.to_account_info();
+        let catalog = &mut ctx.accounts.catalog;
+        if catalog.manager != *acc_user.key {
+            let admin_role = check_role(&ctx.accounts.root_data, &acc_auth, Role::CreateCatalog, acc_user.key, Some(catalog.catalog_id));
+            if admin_role.is_err() {
+                msg!("Not catalog manager or create catalog role");
+                return Err(ErrorCode::AccessDenied.into());
+            }
+        }
+        catalog.fee_mint = Pubkey::default();
+        catalog.fee_vault = Pubkey::default(); // No fee policy
+        catalog.fee_min = 0;
+        catalog.fee_max = 0;
+        catalog.fee_exempt = [Pubkey::default(); MAX_FEE_EXEMPT];
+        msg!("Atellix: Cleared fee policy for Catalog ID: {}", catalog.catalog_id);
+        emit!(FeePolicyUpdatedEvent {
+            user: *acc_user.key,
+            catalog: catalog.catalog_id,
+            fee_mint: catalog.fee_mint,
+            fee_vault: catalog.fee_vault,
+            fee_min: 0,
+            fee_max: 0,
+            fee_exempt: Vec::new(),
+        });
+        Ok(())
+    }
+
+    pub fn create_url(
+        ctx: Context<CreateURL>,
+        inp_url_expand_mode: u8,
+        inp_url_hash: u128,
+        inp_url_length: u32,
+        inp_url: String,
+    ) -> anchor_lang::Result<()> {
+        check_pause(&ctx.accounts.root_data, None, PAUSE_CREATE)?;
+        let confirm_hash: u128 = shake128_hash(inp_url.as_bytes());
+        require!(confirm_hash == inp_url_hash, ErrorCode::InvalidURLHash); // Verifies hash used in the URL account
+        require!(inp_url.len() == inp_url_length as usize, ErrorCode::InvalidURLLength);
+        let expand_mode = URLExpandMode::try_from_primitive(inp_url_expand_mode).map_err(|_| error!(ErrorCode::InvalidURLExpandMode))?;
+        require!(expand_mode.validate_url(&inp_url), ErrorCode::InvalidURLFormat);
+        let url_entry = &mut ctx.accounts.url_entry;
+        url_entry.url_expand_mode = inp_url_expand_mode;
+        url_entry.version = URL_VERSION;
+        url_entry.ready = true;
+        url_entry.ref_count = 0;
+        url_entry.writer = ctx.accounts.admin.key();
+        url_entry.allocated_ts = Clock::get()?.unix_timestamp;
+        if expand_mode.is_binary() {
+            url_entry.url = String::new();
+            url_entry.url_data = utils::content_id_to_bytes(expand_mode, &inp_url).ok_or(error!(ErrorCode::InvalidURLFormat))?;
+        } else {
+            url_entry.url = inp_url;
+            url_entry.url_data = Vec::new();
+        }
+        Ok(())
+    }
+
+    // Allocate a URL account to be written with append_url_chunk and verified by finalize_url
+    pub fn allocate_url(
+        ctx: Context<CreateURL>,
+        inp_url_expand_mode: u8,
+        _inp_url_hash: u128,
+        inp_url_length: u32,
+    ) -> anchor_lang::Result<()> {
+        check_pause(&ctx.accounts.root_data, None, PAUSE_CREATE)?;
+        let expand_mode = URLExpandMode::try_from_primitive(inp_url_expand_mode).map_err(|_| error!(ErrorCode::InvalidURLExpandMode))?;
+        if expand_mode.is_binary() {
+            msg!("Binary URL modes must use create_url");
+            return Err(ErrorCode::InvalidURLExpandMode.into());
+        }
+        let url_entry = &mut ctx.accounts.url_entry;
+        url_entry.url_expand_mode = inp_url_expand_mode;
+        url_entry.version = URL_VERSION;
+        url_entry.ready = false;
+        url_entry.ref_count = 0;
+        url_entry.writer = ctx.accounts.admin.key();
+        url_entry.allocated_ts = Clock::get()?.unix_timestamp;
+        url_entry.url = String::new();
+        url_entry.url_data = vec![0; inp_url_length as usize];
+        Ok(())
+    }
+
+    // Close an unfinalized URL allocation, the writer may abandon it at any time and anyone else after URL_ALLOCATION_TIMEOUT
+    pub fn abandon_url(
+        ctx: Context<AbandonURL>,
+        inp_url_hash: u128,
+    ) -> anchor_lang::Result<()> {
+        let url_entry = &ctx.accounts.url_entry;
+        let user = ctx.accounts.user.key();
+        if user != url_entry.writer {
+            let expires = url_entry.allocated_ts.checked_add(URL_ALLOCATION_TIMEOUT).ok_or(error!(ErrorCode::Overflow))?;
+            if Clock::get()?.unix_timestamp < expires {
+                msg!("URL allocation has not expired");
+                return Err(ErrorCode::AccessDenied.into());
+            }
+        }
+        emit!(CloseURLEvent {
+            user: user,
+            url_entry: url_entry.key(),
+            url_expand_mode: url_entry.url_expand_mode,
+            url_hash: inp_url_hash,
+        });
+        Ok(())
+    }
+
+    pub fn append_url_chunk(
+        ctx: Context<WriteURL>,
+        _inp_url_hash: u128,
+        inp_offset: u32,
+        inp_data: Vec<u8>,
+    ) -> anchor_lang::Result<()> {
+        check_pause(&ctx.accounts.root_data, None, PAUSE_CREATE)?;
+        let url_entry = &mut ctx.accounts.url_entry;
+        let start = inp_offset as usize;
+        let end = start.checked_add(inp_data.len()).ok_or(error!(ErrorCode::Overflow))?;
+        require!(end <= url_entry.url_data.len(), ErrorCode::InvalidURLLength);
+        url_entry.url_data[start..end].copy_from_slice(&inp_data);
+        Ok(())
+    }
+
+    pub fn finalize_url(
+        ctx: Context<WriteURL>,
+        inp_url_hash: u128,
+    ) -> anchor_lang::Result<()> {
+        check_pause(&ctx.accounts.root_data, None, PAUSE_CREATE)?;
+        let url_entry = &mut ctx.accounts.url_entry;
+        let confirm_hash: u128 = shake128_hash(&url_entry.url_data);
+        require!(confirm_hash == inp_url_hash, ErrorCode::InvalidURLHash); // Verifies hash used in the URL account
+        let url_bytes = std::mem::take(&mut url_entry.url_data);
+        let url = String::from_utf8(url_bytes).map_err(|_| error!(ErrorCode::InvalidURLFormat))?;
+        require!(url_entry.expand_mode()?.validate_url(&url), ErrorCode::InvalidURLFormat);
+        url_entry.url = url;
+        url_entry.ready = true;
+        Ok(())
+    }
+
+    // Convert a URL account from the original layout (url_expand_mode, url), references stay untracked
+    pub fn migrate_url(
+        ctx: Context<MigrateURL>,
+        _inp_url_expand_mode: u8,
+        _inp_url_hash: u128,
+    ) -> anchor_lang::Result<()> {
+        let acc_url = &ctx.accounts.url_entry.to_account_info();
+        require!(*acc_url.owner == crate::ID, ErrorCode::InvalidURLAccount);
+        let (url_expand_mode, url) = {
+            let url_data: &[u8] = &acc_url.try_borrow_data()?;
+            require!(CatalogUrl::is_legacy(url_data), ErrorCode::InvalidURLAccount);
+            let mut legacy: &[u8] = &url_data[8..];
+            let mode = u8::deserialize(&mut legacy)?;
+            let url = String::deserialize(&mut legacy)?;
+            (mode, url)
+        };
+        let url_entry = CatalogUrl {
+            url_expand_mode: url_expand_mode,
+            url: url,
+            version: URL_VERSION_UNTRACKED,
+            ready: true,
+            ref_count: 0,
+            writer: Pubkey::default(),
+            allocated_ts: 0,
+            url_data: Vec::new(),
+        };
+        let new_len = CatalogUrl::space(url_expand_mode, url_entry.url.len() as u32);
+        realloc_account(acc_url, &ctx.accounts.payer.to_account_info(), &ctx.accounts.system_program.to_account_info(), new_len)?;
+        let url_data: &mut [u8] = &mut acc_url.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = url_data;
+        url_entry.try_serialize(&mut writer)?;
+        msg!("Atellix: Migrated URL");
+        Ok(())
+    }
+
+    // Set the reference count of a migrated URL, creates and updates should be paused while listings are counted
+    pub fn backfill_url_refs(
+        ctx: Context<BackfillURL>,
+        _inp_url_hash: u128,
+        inp_ref_count: u64,
+    ) -> anchor_lang::Result<()> {
+        let url_entry = &mut ctx.accounts.url_entry;
+        url_entry.ref_count = inp_ref_count;
+        url_entry.version = URL_VERSION;
+        msg!("Atellix: URL references: {}", inp_ref_count);
+        Ok(())
+    }
+
+    pub fn create_listing(
+        ctx: Context<CreateListing>,
         inp_uuid: u128,
     ) -> anchor_lang::Result<()> {
         let clock = Clock::get()?;
+        check_pause(&ctx.accounts.root_data, Some(&ctx.accounts.catalog), PAUSE_CREATE)?;
         let catalog = &mut ctx.accounts.catalog;
         let ix: Instruction = load_instruction_at_checked(0, &ctx.accounts.ix_sysvar)?;
         let (pk, req) = utils::verify_ed25519_ix(&ix, 265)?;
         let params = CatalogParameters::try_from_slice(&req).unwrap();
-        require!(Pubkey::new_from_array(pk.try_into().unwrap()) == catalog.signer, ErrorCode::InvalidParameters);
+        require!(catalog.valid_signer(&Pubkey::new_from_array(pk.try_into().unwrap()), clock.unix_timestamp), ErrorCode::InvalidParameters);
         let owner = Pubkey::new_from_array(params.owner);
         require!(catalog.catalog_id == params.catalog, ErrorCode::InvalidParameters);
         require!(inp_uuid == params.uuid, ErrorCode::InvalidParameters);
         require!(ctx.accounts.owner.key() == owner, ErrorCode::InvalidParameters);
         require!(ctx.accounts.fee_account.key().to_bytes() == params.fee_account, ErrorCode::InvalidParameters);
+        catalog.check_fee(&owner, &ctx.accounts.fee_source.to_account_info(), &ctx.accounts.fee_account.key(), params.fee_tokens, true)?;
+        require!(ctx.accounts.listing_url.key().to_bytes() == params.listing_url, ErrorCode::InvalidParameters);
+        require!(ctx.accounts.label_url.key().to_bytes() == params.label_url, ErrorCode::InvalidParameters);
+        require!(ctx.accounts.detail_url.key().to_bytes() == params.detail_url, ErrorCode::InvalidParameters);
         if params.fee_tokens > 0 {
+            check_pause(&ctx.accounts.root_data, Some(catalog), PAUSE_FEES)?;
             let cpi_accounts = Transfer {
                 from: ctx.accounts.fee_source.to_account_info(),
                 to: ctx.accounts.fee_account.to_account_info(),
@@ -410,6 +2105,9 @@ pub mod catalog {
             let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
             token::transfer(cpi_ctx, params.fee_tokens)?;
         }
+        update_url_refs(&ctx.accounts.listing_url.to_account_info(), true)?;
+        update_url_refs(&ctx.accounts.label_url.to_account_info(), true)?;
+        update_url_refs(&ctx.accounts.detail_url.to_account_info(), true)?;
         let listing_entry = &mut ctx.accounts.listing;
         listing_entry.uuid = params.uuid;
         listing_entry.catalog = params.catalog;
@@ -428,17 +2126,115 @@ pub mod catalog {
         listing_entry.update_count = 0;
         listing_entry.update_ts = clock.unix_timestamp;
         catalog.catalog_counter = catalog.catalog_counter.checked_add(1).ok_or(error!(ErrorCode::Overflow))?;
+        catalog.listing_count = catalog.listing_count.checked_add(1).ok_or(error!(ErrorCode::Overflow))?;
+        Ok(())
+    }
+
+    pub fn update_listing(
+        ctx: Context<UpdateListing>,
+    ) -> anchor_lang::Result<()> {
+        let clock = Clock::get()?;
+        let catalog = &ctx.accounts.catalog;
+        check_pause(&ctx.accounts.root_data, Some(catalog), PAUSE_UPDATE)?;
+        let ix: Instruction = load_instruction_at_checked(0, &ctx.accounts.ix_sysvar)?;
+        let (pk, req) = utils::verify_ed25519_ix(&ix, 273)?;
+        let update = ListingUpdateParameters::try_from_slice(&req).unwrap();
+        let params = update.listing;
+        require!(catalog.valid_signer(&Pubkey::new_from_array(pk.try_into().unwrap()), clock.unix_timestamp), ErrorCode::InvalidParameters);
+        let listing_entry = &mut ctx.accounts.listing;
+        require!(listing_entry.update_count == update.update_seq, ErrorCode::StaleUpdate);
+        require!(listing_entry.uuid == params.uuid, ErrorCode::InvalidParameters);
+        require!(listing_entry.catalog == params.catalog, ErrorCode::InvalidParameters);
+        require!(listing_entry.owner == Pubkey::new_from_array(params.owner), ErrorCode::InvalidParameters);
+        require!(ctx.accounts.fee_account.key().to_bytes() == params.fee_account, ErrorCode::InvalidParameters);
+        catalog.check_fee(&listing_entry.owner, &ctx.accounts.fee_source.to_account_info(), &ctx.accounts.fee_account.key(), params.fee_tokens, false)?;
+        require!(ctx.accounts.listing_url.key().to_bytes() == params.listing_url, ErrorCode::InvalidParameters);
+        require!(ctx.accounts.label_url.key().to_bytes() == params.label_url, ErrorCode::InvalidParameters);
+        require!(ctx.accounts.detail_url.key().to_bytes() == params.detail_url, ErrorCode::InvalidParameters);
+        if params.fee_tokens > 0 {
+            check_pause(&ctx.accounts.root_data, Some(catalog), PAUSE_FEES)?;
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.fee_source.to_account_info(),
+                to: ctx.accounts.fee_account.to_account_info(),
+                authority: ctx.accounts.fee_payer.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            token::transfer(cpi_ctx, params.fee_tokens)?;
+        }
+        // Release the previous URLs before referencing the new ones (accounts may be shared)
+        update_url_refs(&ctx.accounts.prev_listing_url.to_account_info(), false)?;
+        update_url_refs(&ctx.accounts.prev_label_url.to_account_info(), false)?;
+        update_url_refs(&ctx.accounts.prev_detail_url.to_account_info(), false)?;
+        update_url_refs(&ctx.accounts.listing_url.to_account_info(), true)?;
+        update_url_refs(&ctx.accounts.label_url.to_account_info(), true)?;
+        update_url_refs(&ctx.accounts.detail_url.to_account_info(), true)?;
+        listing_entry.category = params.category;
+        listing_entry.filter_by[0] = params.filter_by_1;
+        listing_entry.filter_by[1] = params.filter_by_2;
+        listing_entry.filter_by[2] = params.filter_by_3;
+        listing_entry.attributes = params.attributes;
+        listing_entry.latitude = i32::from_le_bytes(params.latitude);
+        listing_entry.longitude = i32::from_le_bytes(params.longitude);
+        listing_entry.listing_url = Pubkey::new_from_array(params.listing_url);
+        listing_entry.detail_url = Pubkey::new_from_array(params.detail_url);
+        listing_entry.label_url = Pubkey::new_from_array(params.label_url);
+        listing_entry.update_count = listing_entry.update_count.checked_add(1).ok_or(error!(ErrorCode::Overflow))?;
+        listing_entry.update_ts = clock.unix_timestamp;
+        Ok(())
+    }
+
+    pub fn publish_update(
+        ctx: Context<PublishUpdate>,
+    ) -> anchor_lang::Result<()> {
+        let clock = Clock::get()?;
+        check_pause(&ctx.accounts.root_data, Some(&ctx.accounts.catalog), PAUSE_UPDATE)?;
+        let publish_seq = &mut ctx.accounts.publish_seq;
+        publish_seq.publish_count = publish_seq.publish_count.checked_add(1).ok_or(error!(ErrorCode::Overflow))?;
+        let listing_entry = &mut ctx.accounts.listing;
+        listing_entry.update_ts = clock.unix_timestamp;
+        emit!(PublishUpdateEvent {
+            owner: ctx.accounts.owner.key(),
+            catalog: listing_entry.catalog,
+            listing: listing_entry.key(),
+            listing_idx: listing_entry.listing_idx,
+            update_url: ctx.accounts.update_url.key(),
+            update_seq: publish_seq.publish_count,
+            update_ts: listing_entry.update_ts,
+        });
         Ok(())
     }
 
-    // TODO: update_listing
-    // TODO: publish_update
-    // TODO: close_url
+    // Only unreferenced URLs can be closed, migrated URLs must have their references counted with backfill_url_refs first
+    pub fn close_url(
+        ctx: Context<CloseURL>,
+        inp_url_hash: u128,
+    ) -> anchor_lang::Result<()> {
+        let acc_auth = &ctx.accounts.auth_data.to_account_info();
+        let acc_user = &ctx.accounts.auth_user.to_account_info();
+        let admin_role = check_role(&ctx.accounts.root_data, &acc_auth, Role::RemoveURL, acc_user.key, None);
+        if admin_role.is_err() {
+            msg!("No remove URL role");
+            return Err(ErrorCode::AccessDenied.into());
+        }
+        emit!(CloseURLEvent {
+            user: acc_user.key(),
+            url_entry: ctx.accounts.url_entry.key(),
+            url_expand_mode: ctx.accounts.url_entry.url_expand_mode,
+            url_hash: inp_url_hash,
+        });
+        Ok(())
+    }
 
     // All checks performed at account level
     pub fn remove_listing(
         ctx: Context<RemoveListing>,
     ) -> anchor_lang::Result<()> {
+        let catalog = &mut ctx.accounts.catalog;
+        catalog.listing_count = catalog.listing_count.checked_sub(1).ok_or(error!(ErrorCode::Overflow))?;
+        update_url_refs(&ctx.accounts.listing_url.to_account_info(), false)?;
+        update_url_refs(&ctx.accounts.label_url.to_account_info(), false)?;
+        update_url_refs(&ctx.accounts.detail_url.to_account_info(), false)?;
         emit!(RemoveListingEvent {
             user: ctx.accounts.auth_user.key(),
             catalog: ctx.accounts.catalog.catalog_id,
@@ -447,11 +2243,38 @@ pub mod catalog {
         });
         Ok(())
     }
+
+    // All checks performed at account level
+    pub fn close_catalog(
+        ctx: Context<CloseCatalog>,
+    ) -> anchor_lang::Result<()> {
+        ctx.accounts.root_data.remove_catalog()?;
+        let closed = &mut ctx.accounts.catalog_closed;
+        closed.catalog_id = ctx.accounts.catalog.catalog_id;
+        closed.catalog_counter = ctx.accounts.catalog.catalog_counter;
+        closed.closed_ts = Clock::get()?.unix_timestamp;
+        if ctx.accounts.catalog.description_url != Pubkey::default() {  // Migrated catalogs have no description URL
+            update_url_refs(&ctx.accounts.description_url.to_account_info(), false)?;
+        }
+        msg!("Atellix: Closed Catalog ID: {}", ctx.accounts.catalog.catalog_id);
+        emit!(CloseCatalogEvent {
+            user: ctx.accounts.manager.key(),
+            catalog: ctx.accounts.catalog.catalog_id,
+            listing_total: ctx.accounts.catalog.catalog_counter,
+        });
+        Ok(())
+    }
 }
 
 pub mod utils {
     use super::*;
 
+    pub const URL_TEMPLATE_FIELDS: [&str; 4] = ["uuid", "catalog", "listing_idx", "owner"];
+    pub const IPFS_GATEWAY: &str = "https://ipfs.io/ipfs/";
+    pub const ARWEAVE_GATEWAY: &str = "https://arweave.net/";
+
+    const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
     /// Verify Ed25519Program instruction fields
     pub fn verify_ed25519_ix(ix: &Instruction, msg_len: u16) -> anchor_lang::Result<(Vec<u8>, Vec<u8>)> {
         if  ix.program_id       != ED25519_ID                   ||  // The program id we expect
@@ -462,140 +2285,865 @@ pub mod utils {
 
         let r = check_ed25519_data(&ix.data, msg_len)?;            // If that's not the case, check data
 
-        Ok(r)
-    }
+        Ok(r)
+    }
+
+    /// Verify serialized Ed25519Program instruction data
+    pub fn check_ed25519_data(data: &[u8], msg_len: u16) -> anchor_lang::Result<(Vec<u8>, Vec<u8>)> {
+        // According to this layout used by the Ed25519Program
+        // https://github.com/solana-labs/solana-web3.js/blob/master/src/ed25519-program.ts#L33
+
+        // "Deserializing" byte slices
+
+        let num_signatures                  = &[data[0]];        // Byte  0
+        let padding                         = &[data[1]];        // Byte  1
+        let signature_offset                = &data[2..=3];      // Bytes 2,3
+        let signature_instruction_index     = &data[4..=5];      // Bytes 4,5
+        let public_key_offset               = &data[6..=7];      // Bytes 6,7
+        let public_key_instruction_index    = &data[8..=9];      // Bytes 8,9
+        let message_data_offset             = &data[10..=11];    // Bytes 10,11
+        let message_data_size               = &data[12..=13];    // Bytes 12,13
+        let message_instruction_index       = &data[14..=15];    // Bytes 14,15
+
+        let data_pubkey                     = &data[16..16+32];  // Bytes 16..16+32
+        let _data_sig                       = &data[48..48+64];  // Bytes 48..48+64
+        let data_msg                        = &data[112..];      // Bytes 112..end
+
+        // Expected values
+
+        let exp_public_key_offset:      u16 = 16; // 2*u8 + 7*u16
+        let exp_signature_offset:       u16 = exp_public_key_offset + 32;
+        let exp_message_data_offset:    u16 = exp_signature_offset + 64;
+        let exp_num_signatures:          u8 = 1;
+        let exp_message_data_size:      u16 = msg_len;
+
+        // Header and Arg Checks
+
+        // Header
+        if  num_signatures                  != &exp_num_signatures.to_le_bytes()        ||
+            padding                         != &[0]                                     ||
+            signature_offset                != &exp_signature_offset.to_le_bytes()      ||
+            signature_instruction_index     != &u16::MAX.to_le_bytes()                  ||
+            public_key_offset               != &exp_public_key_offset.to_le_bytes()     ||
+            public_key_instruction_index    != &u16::MAX.to_le_bytes()                  ||
+            message_data_offset             != &exp_message_data_offset.to_le_bytes()   ||
+            message_data_size               != &exp_message_data_size.to_le_bytes()     ||
+            message_instruction_index       != &u16::MAX.to_le_bytes()  
+        {
+            msg!("Error 2");
+            return Err(ErrorCode::SigVerificationFailed.into());
+        }
+
+        Ok((data_pubkey.to_vec(), data_msg.to_vec()))
+    }
+
+    /// Decode a percent-encoded string (as produced by encodeURIComponent) into raw bytes
+    pub fn percent_decode(data: &str) -> Option<Vec<u8>> {
+        let bytes = data.as_bytes();
+        let mut res: Vec<u8> = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'%' => {
+                    let hex = bytes.get(i + 1..i + 3)?;
+                    if ! hex.iter().all(u8::is_ascii_hexdigit) {
+                        return None;
+                    }
+                    res.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+                    i += 3;
+                },
+                c if c.is_ascii_graphic() => {
+                    res.push(c);
+                    i += 1;
+                },
+                _ => return None,
+            }
+        }
+        Some(res)
+    }
+
+    /// Format a listing uuid as a lowercase hyphenated UUID string
+    pub fn format_uuid(uuid: u128) -> String {
+        let hex = format!("{:032x}", uuid);
+        format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+    }
+
+    /// Replace {field} placeholders using the lookup function, returns None on unknown fields or unbalanced braces
+    pub fn expand_template<F: Fn(&str) -> Option<String>>(template: &str, lookup: F) -> Option<String> {
+        let mut res = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find(|c| c == '{' || c == '}') {
+            if rest.as_bytes()[start] == b'}' {
+                return None;
+            }
+            let end = start + rest[start..].find('}')?;
+            res.push_str(&rest[..start]);
+            res.push_str(&lookup(&rest[start + 1..end])?);
+            rest = &rest[end + 1..];
+        }
+        res.push_str(rest);
+        Some(res)
+    }
+
+    /// Decode all grants from a raw RBAC account dump ('capacity' is RootData.rbac_capacity)
+    pub fn decode_auth_data(auth_data: &mut [u8], capacity: u32) -> Vec<RoleEntry> {
+        let rd = SlabPageAlloc::new(auth_data);
+        role_page(rd, capacity, 0, capacity).roles
+    }
+
+    /// Template field values for a listing
+    pub fn listing_field(listing: &CatalogEntry, field: &str) -> Option<String> {
+        match field {
+            "uuid" => Some(format_uuid(listing.uuid)),
+            "catalog" => Some(listing.catalog.to_string()),
+            "listing_idx" => Some(listing.listing_idx.to_string()),
+            "owner" => Some(listing.owner.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Expand a URL entry for a listing according to its expand mode
+    pub fn expand_url(url_entry: &CatalogUrl, listing: &CatalogEntry) -> anchor_lang::Result<String> {
+        require!(url_entry.ready, crate::ErrorCode::URLNotReady);
+        let url = match url_entry.expand_mode()? {
+            URLExpandMode::None => Some(url_entry.url.clone()),
+            URLExpandMode::AppendUUID => Some(format!("{}{}", url_entry.url, format_uuid(listing.uuid))),
+            URLExpandMode::UTF8UriEncoded => percent_decode(&url_entry.url).and_then(|v| String::from_utf8(v).ok()),
+            URLExpandMode::Template => expand_template(&url_entry.url, |f| listing_field(listing, f)),
+            URLExpandMode::IPFSContent => content_id_from_bytes(URLExpandMode::IPFSContent, &url_entry.url_data).map(|cid| format!("{}{}", IPFS_GATEWAY, cid)),
+            URLExpandMode::ArweaveTx => content_id_from_bytes(URLExpandMode::ArweaveTx, &url_entry.url_data).map(|tx| format!("{}{}", ARWEAVE_GATEWAY, tx)),
+        };
+        url.ok_or(error!(crate::ErrorCode::InvalidURLFormat))
+    }
+
+    /// Decode a textual IPFS CID or Arweave transaction id into its compact binary form
+    pub fn content_id_to_bytes(mode: URLExpandMode, text: &str) -> Option<Vec<u8>> {
+        let data = match mode {
+            URLExpandMode::IPFSContent => {
+                if text.len() == 46 && text.starts_with("Qm") {
+                    bs58::decode(text).into_vec().ok()?
+                } else if let Some(rest) = text.strip_prefix('b') {
+                    base32_decode(rest)?
+                } else {
+                    return None;
+                }
+            },
+            URLExpandMode::ArweaveTx => {
+                if text.len() != 43 {
+                    return None;
+                }
+                base64::decode_config(text, base64::URL_SAFE_NO_PAD).ok()?
+            },
+            _ => return None,
+        };
+        // Only accept canonical encodings so the URL hash identifies a single account
+        if content_id_from_bytes(mode, &data)? != text {
+            return None;
+        }
+        Some(data)
+    }
+
+    /// Encode a binary IPFS CID or Arweave transaction id back into its textual form
+    pub fn content_id_from_bytes(mode: URLExpandMode, data: &[u8]) -> Option<String> {
+        match mode {
+            URLExpandMode::IPFSContent => {
+                if data.len() == 34 && data[0] == 0x12 && data[1] == 0x20 { // CIDv0: sha2-256 multihash
+                    Some(bs58::encode(data).into_string())
+                } else if data.first() == Some(&0x01) && valid_cid_v1(data) {
+                    Some(format!("b{}", base32_encode(data)))
+                } else {
+                    None
+                }
+            },
+            URLExpandMode::ArweaveTx => {
+                if data.len() != 32 {
+                    return None;
+                }
+                Some(base64::encode_config(data, base64::URL_SAFE_NO_PAD))
+            },
+            _ => None,
+        }
+    }
+
+    /// Binary length of a content id given the length of its textual form
+    pub fn content_id_length(mode: URLExpandMode, text_length: usize) -> usize {
+        match mode {
+            URLExpandMode::IPFSContent if text_length == 46 => 34,
+            URLExpandMode::IPFSContent => text_length.saturating_sub(1) * 5 / 8,
+            URLExpandMode::ArweaveTx => 32,
+            _ => text_length,
+        }
+    }
+
+    // CIDv1: <version><codec varint><hash code varint><digest length varint><digest>
+    fn valid_cid_v1(data: &[u8]) -> bool {
+        let mut pos: usize = 1;
+        let parsed = read_varint(data, &mut pos)
+            .and_then(|_codec| read_varint(data, &mut pos))
+            .and_then(|_hash_code| read_varint(data, &mut pos));
+        match parsed {
+            Some(digest_len) => data.len().checked_sub(pos) == Some(digest_len as usize),
+            None => false,
+        }
+    }
+
+    fn read_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+        let mut value: u64 = 0;
+        for shift in (0..63).step_by(7) {
+            let b = *data.get(*pos)?;
+            *pos += 1;
+            value |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn base32_encode(data: &[u8]) -> String {
+        let mut res = String::with_capacity((data.len() * 8 + 4) / 5);
+        let mut buffer: u16 = 0;
+        let mut bits: u32 = 0;
+        for b in data {
+            buffer = (buffer << 8) | *b as u16;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                res.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+            }
+        }
+        if bits > 0 {
+            res.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+        }
+        res
+    }
+
+    fn base32_decode(text: &str) -> Option<Vec<u8>> {
+        let mut res: Vec<u8> = Vec::with_capacity(text.len() * 5 / 8);
+        let mut buffer: u16 = 0;
+        let mut bits: u32 = 0;
+        for c in text.bytes() {
+            let val = BASE32_ALPHABET.iter().position(|&x| x == c)? as u16;
+            buffer = (buffer << 5) | val;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                res.push((buffer >> bits) as u8);
+            }
+        }
+        Some(res)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_percent_decode() {
+            assert_eq!(percent_decode("caf%C3%A9%20menu").unwrap(), "café menu".as_bytes());
+            assert_eq!(percent_decode("%2f%2F").unwrap(), b"//");
+            assert_eq!(percent_decode("").unwrap(), b"");
+            assert!(percent_decode("%4").is_none());         // Truncated escape
+            assert!(percent_decode("%zz").is_none());        // Invalid hex
+            assert!(percent_decode("%+1").is_none());        // Sign accepted by from_str_radix
+            assert!(percent_decode("a b").is_none());        // Unencoded space
+            assert!(percent_decode("caf\u{e9}").is_none());  // Unencoded non-ASCII
+        }
+
+        #[test]
+        fn test_validate_url() {
+            assert!(URLExpandMode::try_from_primitive(6).is_err());
+            assert!(URLExpandMode::AppendUUID.validate_url("https://example.com/item/"));
+            assert!(URLExpandMode::AppendUUID.validate_url("https://example.com/item?id="));
+            assert!(! URLExpandMode::AppendUUID.validate_url("https://example.com/item"));
+            assert!(URLExpandMode::UTF8UriEncoded.validate_url("%7B%22name%22%3A%22caf%C3%A9%22%7D"));
+            assert!(! URLExpandMode::UTF8UriEncoded.validate_url("%FF%FE"));  // Not UTF-8
+            assert!(! URLExpandMode::UTF8UriEncoded.validate_url("{\"name\": 1}"));
+        }
+
+        #[test]
+        fn test_expand_template() {
+            let listing = CatalogEntry {
+                uuid: 0x3c57b88796c34263b43739420c7ea541,
+                catalog: 2,
+                listing_idx: 17,
+                ..Default::default()
+            };
+            let lookup = |f: &str| listing_field(&listing, f);
+            assert_eq!(format_uuid(listing.uuid), "3c57b887-96c3-4263-b437-39420c7ea541");
+            assert_eq!(format_uuid(1), "00000000-0000-0000-0000-000000000001");
+            assert_eq!(expand_template("https://shop.example/{uuid}/detail?c={catalog}&i={listing_idx}", lookup).unwrap(),
+                "https://shop.example/3c57b887-96c3-4263-b437-39420c7ea541/detail?c=2&i=17");
+            assert_eq!(expand_template("{owner}", lookup).unwrap(), Pubkey::default().to_string());
+            assert_eq!(expand_template("no placeholders", lookup).unwrap(), "no placeholders");
+            assert!(expand_template("https://shop.example/{sku}", lookup).is_none());   // Unknown field
+            assert!(expand_template("https://shop.example/{}", lookup).is_none());      // Empty field
+            assert!(expand_template("https://shop.example/{uuid", lookup).is_none());   // Unclosed
+            assert!(expand_template("https://shop.example/uuid}", lookup).is_none());   // Unopened
+            assert!(expand_template("https://shop.example/{{uuid}}", lookup).is_none()); // Nested
+        }
+
+        #[test]
+        fn test_expand_url_template() {
+            let listing = CatalogEntry { uuid: 1, catalog: 5, ..Default::default() };
+            let url_entry = CatalogUrl {
+                url_expand_mode: URLExpandMode::Template as u8,
+                url: "https://shop.example/{catalog}/{uuid}".to_string(),
+                version: URL_VERSION,
+                ready: true,
+                ..Default::default()
+            };
+            assert!(URLExpandMode::Template.validate_url(&url_entry.url));
+            assert!(! URLExpandMode::Template.validate_url("https://shop.example/{price}"));
+            assert_eq!(expand_url(&url_entry, &listing).unwrap(), "https://shop.example/5/00000000-0000-0000-0000-000000000001");
+            let pending = CatalogUrl { ready: false, ..url_entry };
+            assert!(expand_url(&pending, &listing).is_err());
+        }
+
+        const CID_V0: &str = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+        const CID_V1: &str = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+        const ARWEAVE_TX: &str = "bNbA3TEQVL60xlgCcqdz4ZPHFZ711cZ3hmkpGttDt_U";
+
+        #[test]
+        fn test_content_id_round_trip() {
+            let v0 = content_id_to_bytes(URLExpandMode::IPFSContent, CID_V0).unwrap();
+            assert_eq!(v0.len(), 34);
+            assert_eq!(&v0[..2], &[0x12, 0x20]);
+            assert_eq!(content_id_from_bytes(URLExpandMode::IPFSContent, &v0).unwrap(), CID_V0);
+            assert_eq!(content_id_length(URLExpandMode::IPFSContent, CID_V0.len()), v0.len());
+
+            let v1 = content_id_to_bytes(URLExpandMode::IPFSContent, CID_V1).unwrap();
+            assert_eq!(&v1[..4], &[0x01, 0x70, 0x12, 0x20]);    // CIDv1, dag-pb, sha2-256, 32 byte digest
+            assert_eq!(v1.len(), 36);
+            assert_eq!(content_id_from_bytes(URLExpandMode::IPFSContent, &v1).unwrap(), CID_V1);
+            assert_eq!(content_id_length(URLExpandMode::IPFSContent, CID_V1.len()), v1.len());
+
+            let tx = content_id_to_bytes(URLExpandMode::ArweaveTx, ARWEAVE_TX).unwrap();
+            assert_eq!(tx.len(), 32);
+            assert_eq!(content_id_from_bytes(URLExpandMode::ArweaveTx, &tx).unwrap(), ARWEAVE_TX);
+            assert_eq!(content_id_length(URLExpandMode::ArweaveTx, ARWEAVE_TX.len()), tx.len());
+
+            let url_entry = CatalogUrl {
+                url_expand_mode: URLExpandMode::IPFSContent as u8,
+                version: URL_VERSION,
+                ready: true,
+                url_data: v1,
+                ..Default::default()
+            };
+            assert_eq!(expand_url(&url_entry, &CatalogEntry::default()).unwrap(), format!("{}{}", IPFS_GATEWAY, CID_V1));
+        }
+
+        #[test]
+        fn test_content_id_non_canonical() {
+            // Non-zero trailing bits decode to the same bytes as the canonical form
+            let v1_trailing = format!("{}j", &CID_V1[..CID_V1.len() - 1]);
+            assert_eq!(base32_decode(&v1_trailing[1..]), base32_decode(&CID_V1[1..]));
+            assert!(content_id_to_bytes(URLExpandMode::IPFSContent, &v1_trailing).is_none());
+            let tx_trailing = format!("{}V", &ARWEAVE_TX[..ARWEAVE_TX.len() - 1]);
+            assert!(content_id_to_bytes(URLExpandMode::ArweaveTx, &tx_trailing).is_none());
+
+            assert!(content_id_to_bytes(URLExpandMode::IPFSContent, &CID_V1.to_uppercase()).is_none());  // Other multibase
+            assert!(content_id_to_bytes(URLExpandMode::IPFSContent, &CID_V0[..45]).is_none());
+            assert!(content_id_to_bytes(URLExpandMode::IPFSContent, "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbd0").is_none());  // '0' is not base58
+            assert!(content_id_to_bytes(URLExpandMode::IPFSContent, &CID_V1[..CID_V1.len() - 2]).is_none());  // Short digest
+            assert!(content_id_to_bytes(URLExpandMode::ArweaveTx, &ARWEAVE_TX.replace('_', "/")).is_none()); // Standard alphabet
+            assert!(content_id_to_bytes(URLExpandMode::ArweaveTx, &format!("{}=", ARWEAVE_TX)).is_none());
+            assert!(content_id_to_bytes(URLExpandMode::None, CID_V0).is_none());
+            assert!(content_id_from_bytes(URLExpandMode::ArweaveTx, &[0u8; 31]).is_none());
+            assert!(content_id_from_bytes(URLExpandMode::IPFSContent, &[0x12, 0x20, 0x00]).is_none());
+        }
+
+        #[test]
+        fn test_varint_and_base32() {
+            let mut pos = 0;
+            assert_eq!(read_varint(&[0x70], &mut pos), Some(0x70));
+            assert_eq!(pos, 1);
+            pos = 0;
+            assert_eq!(read_varint(&[0x80, 0x01], &mut pos), Some(128));
+            assert_eq!(pos, 2);
+            pos = 0;
+            assert_eq!(read_varint(&[0x80], &mut pos), None);  // Truncated
+            pos = 0;
+            assert_eq!(read_varint(&[0xff; 10], &mut pos), None); // Longer than 9 bytes
+            assert!(valid_cid_v1(&[0x01, 0x55, 0x12, 0x02, 0xaa, 0xbb]));
+            assert!(! valid_cid_v1(&[0x01, 0x55, 0x12, 0x03, 0xaa, 0xbb])); // Digest length mismatch
+            assert!(! valid_cid_v1(&[0x01, 0x55]));
+            assert_eq!(base32_encode(b"hello"), "nbswy3dp");
+            assert_eq!(base32_decode("nbswy3dp").unwrap(), b"hello");
+            assert_eq!(base32_encode(&[0xff]), "74");
+            assert!(base32_decode("nbswy3d1").is_none());
+        }
+    }
+
+    #[error_code]
+    pub enum ErrorCode {
+        #[msg("Signature verification failed")]
+        SigVerificationFailed,
+    }
+}
+
+// Initialize stays upgrade authority only: RootData (and super_admin) does not exist yet, so is_program_owner cannot apply,
+// and allowing any signer on an immutable program would let the first caller take ownership
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(init, seeds = [program_id.as_ref()], bump, payer = program_admin, space = 236)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    #[account(mut, constraint = full_account_zero(&auth_data))]
+    pub auth_data: UncheckedAccount<'info>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(program_admin.key()))]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub program_admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TransferSuperAdmin<'info> {
+    #[account(mut, seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    #[account(constraint = is_program_owner(&program_data, &root_data, &program_admin.key()))]
+    pub program_data: Account<'info, ProgramData>,
+    pub program_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptSuperAdmin<'info> {
+    #[account(mut, seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    #[account(constraint = root_data.pending_super_admin == new_admin.key())]
+    pub new_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMetadata<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    #[account(constraint = is_program_owner(&program_data, &root_data, &program_admin.key()))]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub program_admin: Signer<'info>,
+    #[account(init_if_needed, seeds = [program_id.as_ref(), b"metadata"], bump, payer = program_admin, space = 584)]
+    pub program_info: Account<'info, ProgramMetadata>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(_inp_root_nonce: u8)]
+pub struct UpdateRBAC<'info> {
+    #[account(seeds = [program_id.as_ref()], bump = _inp_root_nonce)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    #[account(mut, constraint = root_data.root_authority == auth_data.key())]
+    pub auth_data: UncheckedAccount<'info>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub program_admin: Signer<'info>,
+    /// CHECK: ok
+    pub rbac_user: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ListRoles<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    #[account(constraint = root_data.root_authority == auth_data.key())]
+    pub auth_data: UncheckedAccount<'info>,
+}
 
-    /// Verify serialized Ed25519Program instruction data
-    pub fn check_ed25519_data(data: &[u8], msg_len: u16) -> anchor_lang::Result<(Vec<u8>, Vec<u8>)> {
-        // According to this layout used by the Ed25519Program
-        // https://github.com/solana-labs/solana-web3.js/blob/master/src/ed25519-program.ts#L33
+#[derive(Accounts)]
+pub struct UpdateRootAuthority<'info> {
+    #[account(mut, seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    #[account(mut, constraint = root_data.root_authority == auth_data.key())]
+    pub auth_data: UncheckedAccount<'info>,
+    /// CHECK: ok
+    #[account(mut, constraint = full_account_zero(&new_auth_data))]
+    pub new_auth_data: UncheckedAccount<'info>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    #[account(constraint = is_program_owner(&program_data, &root_data, &program_admin.key()))]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub program_admin: Signer<'info>,
+}
 
-        // "Deserializing" byte slices
+#[derive(Accounts)]
+pub struct UpdateRBACMode<'info> {
+    #[account(mut, seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    #[account(constraint = root_data.root_authority == auth_data.key())]
+    pub auth_data: UncheckedAccount<'info>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    #[account(constraint = is_program_owner(&program_data, &root_data, &program_admin.key()))]
+    pub program_data: Account<'info, ProgramData>,
+    pub program_admin: Signer<'info>,
+}
 
-        let num_signatures                  = &[data[0]];        // Byte  0
-        let padding                         = &[data[1]];        // Byte  1
-        let signature_offset                = &data[2..=3];      // Bytes 2,3
-        let signature_instruction_index     = &data[4..=5];      // Bytes 4,5
-        let public_key_offset               = &data[6..=7];      // Bytes 6,7
-        let public_key_instruction_index    = &data[8..=9];      // Bytes 8,9
-        let message_data_offset             = &data[10..=11];    // Bytes 10,11
-        let message_data_size               = &data[12..=13];    // Bytes 12,13
-        let message_instruction_index       = &data[14..=15];    // Bytes 14,15
+#[derive(Accounts)]
+pub struct PruneRoleAccounts<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+}
 
-        let data_pubkey                     = &data[16..16+32];  // Bytes 16..16+32
-        let _data_sig                       = &data[48..48+64];  // Bytes 48..48+64
-        let data_msg                        = &data[112..];      // Bytes 112..end
+#[derive(Accounts)]
+pub struct CopyRBAC<'info> {
+    #[account(mut, seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    #[account(mut, constraint = root_data.root_authority == auth_data.key())]
+    pub auth_data: UncheckedAccount<'info>,
+    /// CHECK: ok
+    #[account(mut, constraint = root_data.pending_authority == new_auth_data.key() @ ErrorCode::InvalidParameters)]
+    pub new_auth_data: UncheckedAccount<'info>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    #[account(constraint = is_program_owner(&program_data, &root_data, &program_admin.key()))]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub program_admin: Signer<'info>,
+}
 
-        // Expected values
+#[derive(Accounts)]
+pub struct MigrateRootData<'info> {
+    /// CHECK: ok
+    #[account(mut, seeds = [program_id.as_ref()], bump)]
+    pub root_data: UncheckedAccount<'info>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(program_admin.key()))]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub program_admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
 
-        let exp_public_key_offset:      u16 = 16; // 2*u8 + 7*u16
-        let exp_signature_offset:       u16 = exp_public_key_offset + 32;
-        let exp_message_data_offset:    u16 = exp_signature_offset + 64;
-        let exp_num_signatures:          u8 = 1;
-        let exp_message_data_size:      u16 = msg_len;
+#[derive(Accounts)]
+pub struct UpdateRootConfig<'info> {
+    #[account(mut, seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    #[account(constraint = is_program_owner(&program_data, &root_data, &program_admin.key()))]
+    pub program_data: Account<'info, ProgramData>,
+    pub program_admin: Signer<'info>,
+}
 
-        // Header and Arg Checks
+#[derive(Accounts)]
+#[instruction(inp_proposal_id: u64)]
+pub struct ProposeAdminAction<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    pub auth_data: UncheckedAccount<'info>,     // Root authority or the proposer's RoleGrant account (checked by check_role)
+    #[account(init, seeds = [b"proposal", inp_proposal_id.to_be_bytes().as_ref()], bump, payer = proposer, space = 446)]
+    pub proposal: Account<'info, AdminProposal>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    /// CHECK: ok
+    pub rbac_user: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
 
-        // Header
-        if  num_signatures                  != &exp_num_signatures.to_le_bytes()        ||
-            padding                         != &[0]                                     ||
-            signature_offset                != &exp_signature_offset.to_le_bytes()      ||
-            signature_instruction_index     != &u16::MAX.to_le_bytes()                  ||
-            public_key_offset               != &exp_public_key_offset.to_le_bytes()     ||
-            public_key_instruction_index    != &u16::MAX.to_le_bytes()                  ||
-            message_data_offset             != &exp_message_data_offset.to_le_bytes()   ||
-            message_data_size               != &exp_message_data_size.to_le_bytes()     ||
-            message_instruction_index       != &u16::MAX.to_le_bytes()  
-        {
-            msg!("Error 2");
-            return Err(ErrorCode::SigVerificationFailed.into());
-        }
+#[derive(Accounts)]
+#[instruction(inp_proposal_id: u64)]
+pub struct ApproveAdminAction<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    pub auth_data: UncheckedAccount<'info>,     // Root authority or the approver's RoleGrant account (checked by check_role)
+    #[account(mut, seeds = [b"proposal", inp_proposal_id.to_be_bytes().as_ref()], bump)]
+    pub proposal: Account<'info, AdminProposal>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    pub program_data: Account<'info, ProgramData>,
+    pub approver: Signer<'info>,
+}
 
-        Ok((data_pubkey.to_vec(), data_msg.to_vec()))
-    }
+#[derive(Accounts)]
+#[instruction(inp_proposal_id: u64)]
+pub struct ExecuteAdminAction<'info> {
+    #[account(mut, seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    #[account(mut, constraint = root_data.root_authority == auth_data.key())]
+    pub auth_data: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"proposal", inp_proposal_id.to_be_bytes().as_ref()], bump, close = proposer)]
+    pub proposal: Account<'info, AdminProposal>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    pub program_data: Account<'info, ProgramData>,
+    /// CHECK: ok
+    #[account(mut, address = proposal.proposer)]
+    pub proposer: AccountInfo<'info>,
+    /// CHECK: ok
+    #[account(mut)]
+    pub role_grant: UncheckedAccount<'info>,    // RoleGrant account for the proposal in RBACMode::Accounts (checked by role_grant_bump)
+    #[account(mut)]
+    pub payer: Signer<'info>,                   // Pays for new RoleGrant accounts
+    /// CHECK: ok
+    #[account(mut)]
+    pub grant_payer: UncheckedAccount<'info>,   // Receives the rent of a revoked RoleGrant (checked against RoleGrant.payer)
+    pub system_program: Program<'info, System>,
+}
 
-    #[error_code]
-    pub enum ErrorCode {
-        #[msg("Signature verification failed")]
-        SigVerificationFailed,
-    }
+#[derive(Accounts)]
+#[instruction(inp_proposal_id: u64)]
+pub struct CancelAdminAction<'info> {
+    #[account(mut, seeds = [b"proposal", inp_proposal_id.to_be_bytes().as_ref()], bump, close = proposer)]
+    pub proposal: Account<'info, AdminProposal>,
+    pub user: Signer<'info>,
+    /// CHECK: ok
+    #[account(mut, address = proposal.proposer)]
+    pub proposer: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(init, seeds = [program_id.as_ref()], bump, payer = program_admin, space = 48)]
+#[instruction(_inp_root_nonce: u8)]
+pub struct RenounceRole<'info> {
+    #[account(seeds = [program_id.as_ref()], bump = _inp_root_nonce)]
     pub root_data: Account<'info, RootData>,
     /// CHECK: ok
-    #[account(mut, constraint = full_account_zero(&auth_data))]
+    #[account(mut, constraint = root_data.root_authority == auth_data.key())]
     pub auth_data: UncheckedAccount<'info>,
+    pub rbac_user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_inp_root_nonce: u8, inp_role: u32, inp_catalog: Option<u64>)]
+pub struct GrantRoleAccount<'info> {
+    #[account(seeds = [program_id.as_ref()], bump = _inp_root_nonce)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    pub admin_grant: UncheckedAccount<'info>,
     #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
     pub program: Program<'info, Catalog>,
-    #[account(constraint = program_data.upgrade_authority_address == Some(program_admin.key()))]
     pub program_data: Account<'info, ProgramData>,
     #[account(mut)]
     pub program_admin: Signer<'info>,
+    /// CHECK: ok
+    pub rbac_user: AccountInfo<'info>,
+    #[account(init_if_needed, seeds = [b"role", inp_role.to_be_bytes().as_ref(), rbac_user.key().as_ref(), role_scope(inp_catalog).as_ref()], bump,
+        payer = program_admin, space = 101)]
+    pub role_grant: Account<'info, RoleGrant>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateMetadata<'info> {
+#[instruction(_inp_root_nonce: u8, inp_role: u32, inp_catalog: Option<u64>)]
+pub struct RevokeRoleAccount<'info> {
+    #[account(seeds = [program_id.as_ref()], bump = _inp_root_nonce)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    pub admin_grant: UncheckedAccount<'info>,
     #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
     pub program: Program<'info, Catalog>,
-    #[account(constraint = program_data.upgrade_authority_address == Some(program_admin.key()))]
     pub program_data: Account<'info, ProgramData>,
     #[account(mut)]
     pub program_admin: Signer<'info>,
-    #[account(init_if_needed, seeds = [program_id.as_ref(), b"metadata"], bump, payer = program_admin, space = 584)]
-    pub program_info: Account<'info, ProgramMetadata>,
-    pub system_program: Program<'info, System>,
+    /// CHECK: ok
+    pub rbac_user: AccountInfo<'info>,
+    #[account(mut, seeds = [b"role", inp_role.to_be_bytes().as_ref(), rbac_user.key().as_ref(), role_scope(inp_catalog).as_ref()], bump, close = payer)]
+    pub role_grant: Account<'info, RoleGrant>,
+    /// CHECK: ok
+    #[account(mut, address = role_grant.payer)]
+    pub payer: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(_inp_root_nonce: u8)]
-pub struct UpdateRBAC<'info> {
+#[instruction(_inp_root_nonce: u8, inp_role: u32, inp_catalog: Option<u64>)]
+pub struct RenounceRoleAccount<'info> {
     #[account(seeds = [program_id.as_ref()], bump = _inp_root_nonce)]
     pub root_data: Account<'info, RootData>,
+    #[account(mut)]
+    pub rbac_user: Signer<'info>,
+    #[account(mut, seeds = [b"role", inp_role.to_be_bytes().as_ref(), rbac_user.key().as_ref(), role_scope(inp_catalog).as_ref()], bump, close = payer)]
+    pub role_grant: Account<'info, RoleGrant>,
+    /// CHECK: ok
+    #[account(mut, address = role_grant.payer)]
+    pub payer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_inp_root_nonce: u8, inp_role: u32, inp_catalog: Option<u64>)]
+pub struct MigrateRoleAccount<'info> {
+    #[account(mut, seeds = [program_id.as_ref()], bump = _inp_root_nonce)]
+    pub root_data: Account<'info, RootData>,
     /// CHECK: ok
     #[account(mut, constraint = root_data.root_authority == auth_data.key())]
     pub auth_data: UncheckedAccount<'info>,
     #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
     pub program: Program<'info, Catalog>,
+    #[account(constraint = is_program_owner(&program_data, &root_data, &program_admin.key()))]
     pub program_data: Account<'info, ProgramData>,
     #[account(mut)]
     pub program_admin: Signer<'info>,
     /// CHECK: ok
     pub rbac_user: AccountInfo<'info>,
+    #[account(init, seeds = [b"role", inp_role.to_be_bytes().as_ref(), rbac_user.key().as_ref(), role_scope(inp_catalog).as_ref()], bump,
+        payer = program_admin, space = 101)]
+    pub role_grant: Account<'info, RoleGrant>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(_inp_root_nonce: u8)]
+pub struct PruneRBAC<'info> {
+    #[account(seeds = [program_id.as_ref()], bump = _inp_root_nonce)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    #[account(mut, constraint = root_data.root_authority == auth_data.key())]
+    pub auth_data: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_inp_root_nonce: u8)]
+pub struct UpdateNetworkPause<'info> {
+    #[account(mut, seeds = [program_id.as_ref()], bump = _inp_root_nonce)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    pub auth_data: UncheckedAccount<'info>,     // Root authority or RoleGrant account (checked by check_role)
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    pub program_data: Account<'info, ProgramData>,
+    pub auth_user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_inp_root_nonce: u8)]
+pub struct UpdateCatalogPause<'info> {
+    #[account(seeds = [program_id.as_ref()], bump = _inp_root_nonce)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    pub auth_data: UncheckedAccount<'info>,     // Root authority or RoleGrant account (checked by check_role)
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    pub program_data: Account<'info, ProgramData>,
+    pub auth_user: Signer<'info>,
+    #[account(mut)]
+    pub catalog: Account<'info, CatalogInstance>,
+}
+
+#[derive(Accounts)]
+#[instruction(inp_catalog: u64, inp_name_hash: u128)]
+pub struct CreateCatalog<'info> {
+    #[account(mut, seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    pub auth_data: UncheckedAccount<'info>,     // Root authority or RoleGrant account (checked by check_role)
+    pub auth_user: Signer<'info>,
+    #[account(init, seeds = [b"catalog", inp_catalog.to_be_bytes().as_ref()], bump, payer = fee_payer, space = 578)]
+    pub catalog: Account<'info, CatalogInstance>,
+    #[account(init, seeds = [b"catalog_name", inp_name_hash.to_be_bytes().as_ref()], bump, payer = fee_payer, space = 48)]
+    pub catalog_name: Account<'info, CatalogName>,
+    /// CHECK: ok
+    #[account(seeds = [b"catalog_closed", inp_catalog.to_be_bytes().as_ref()], bump, constraint = catalog_closed.data_is_empty() @ ErrorCode::CatalogClosed)]
+    pub catalog_closed: UncheckedAccount<'info>,
+    /// CHECK: ok
+    #[account(mut)]
+    pub description_url: UncheckedAccount<'info>,
+    /// CHECK: ok
+    pub catalog_signer: UncheckedAccount<'info>,
+    /// CHECK: ok
+    pub catalog_manager: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub fee_payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(inp_catalog: u64, inp_listing_count: u64, inp_name_hash: u128)]
+pub struct MigrateCatalog<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    #[account(mut, seeds = [b"catalog", inp_catalog.to_be_bytes().as_ref()], bump)]
+    pub catalog: UncheckedAccount<'info>,
+    #[account(init, seeds = [b"catalog_name", inp_name_hash.to_be_bytes().as_ref()], bump, payer = program_admin, space = 48)]
+    pub catalog_name: Account<'info, CatalogName>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    #[account(constraint = is_program_owner(&program_data, &root_data, &program_admin.key()))]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub program_admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(inp_catalog: u64)]
+pub struct SetListingCount<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    #[account(mut, seeds = [b"catalog", inp_catalog.to_be_bytes().as_ref()], bump)]
+    pub catalog: Account<'info, CatalogInstance>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    #[account(constraint = is_program_owner(&program_data, &root_data, &program_admin.key()))]
+    pub program_data: Account<'info, ProgramData>,
+    pub program_admin: Signer<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(inp_catalog: u64)]
-pub struct CreateCatalog<'info> {
-    #[account(mut, seeds = [program_id.as_ref()], bump)]
+pub struct UpdateCatalog<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
     pub root_data: Account<'info, RootData>,
     /// CHECK: ok
-    #[account(constraint = root_data.root_authority == auth_data.key())]
-    pub auth_data: UncheckedAccount<'info>,
+    pub auth_data: UncheckedAccount<'info>,     // Root authority or RoleGrant account (checked by check_role)
     pub auth_user: Signer<'info>,
-    #[account(init, seeds = [b"catalog", inp_catalog.to_be_bytes().as_ref()], bump, payer = fee_payer, space = 88)]
+    #[account(mut, seeds = [b"catalog", catalog.catalog_id.to_be_bytes().as_ref()], bump)]
     pub catalog: Account<'info, CatalogInstance>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFeePolicy<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
     /// CHECK: ok
-    pub catalog_signer: UncheckedAccount<'info>,
-    /// CHECK: ok
-    pub catalog_manager: UncheckedAccount<'info>,
-    #[account(mut)]
-    pub fee_payer: Signer<'info>,
-    pub system_program: Program<'info, System>,
+    pub auth_data: UncheckedAccount<'info>,     // Root authority or RoleGrant account (checked by check_role)
+    pub auth_user: Signer<'info>,
+    #[account(mut, seeds = [b"catalog", catalog.catalog_id.to_be_bytes().as_ref()], bump)]
+    pub catalog: Account<'info, CatalogInstance>,
+    pub fee_mint: Account<'info, Mint>,
+    #[account(constraint = fee_vault.mint == fee_mint.key())]
+    pub fee_vault: Account<'info, TokenAccount>,
 }
 
 #[derive(Accounts)]
 #[instruction(inp_uuid: u128)]
 pub struct CreateListing<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
     #[account(mut)]
     pub catalog: Account<'info, CatalogInstance>,
     #[account(init, seeds = [catalog.catalog_id.to_be_bytes().as_ref(), inp_uuid.to_be_bytes().as_ref()], bump, payer = fee_payer, space = 257)]
     pub listing: Account<'info, CatalogEntry>,
     pub owner: Signer<'info>,
     /// CHECK: ok
+    #[account(mut)]
+    pub listing_url: UncheckedAccount<'info>,
+    /// CHECK: ok
+    #[account(mut)]
+    pub label_url: UncheckedAccount<'info>,
+    /// CHECK: ok
+    #[account(mut)]
+    pub detail_url: UncheckedAccount<'info>,
+    /// CHECK: ok
     #[account(address = IX_ID)]
     pub ix_sysvar: AccountInfo<'info>,
     #[account(mut)]
@@ -613,34 +3161,208 @@ pub struct CreateListing<'info> {
 }
 
 #[derive(Accounts)]
-pub struct RemoveListing<'info> {
+pub struct UpdateListing<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    #[account(constraint = catalog.catalog_id == listing.catalog)]
+    pub catalog: Account<'info, CatalogInstance>,
+    #[account(mut, seeds = [catalog.catalog_id.to_be_bytes().as_ref(), listing.uuid.to_be_bytes().as_ref()], bump)]
+    pub listing: Account<'info, CatalogEntry>,
+    #[account(constraint = listing.owner == owner.key())]
+    pub owner: Signer<'info>,
+    /// CHECK: ok
+    #[account(mut, address = listing.listing_url)]
+    pub prev_listing_url: UncheckedAccount<'info>,
+    /// CHECK: ok
+    #[account(mut, address = listing.label_url)]
+    pub prev_label_url: UncheckedAccount<'info>,
+    /// CHECK: ok
+    #[account(mut, address = listing.detail_url)]
+    pub prev_detail_url: UncheckedAccount<'info>,
+    /// CHECK: ok
+    #[account(mut)]
+    pub listing_url: UncheckedAccount<'info>,
+    /// CHECK: ok
+    #[account(mut)]
+    pub label_url: UncheckedAccount<'info>,
+    /// CHECK: ok
+    #[account(mut)]
+    pub detail_url: UncheckedAccount<'info>,
+    /// CHECK: ok
+    #[account(address = IX_ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+    pub fee_payer: Signer<'info>,
+    /// CHECK: ok
+    #[account(mut)]
+    pub fee_source: AccountInfo<'info>,
+    /// CHECK: ok
+    #[account(mut)]
+    pub fee_account: AccountInfo<'info>,
+    /// CHECK: ok
+    #[account(address = token::ID)]
+    pub token_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PublishUpdate<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
     #[account(constraint = catalog.catalog_id == listing.catalog)]
     pub catalog: Account<'info, CatalogInstance>,
+    #[account(mut)]
+    pub listing: Account<'info, CatalogEntry>,
+    #[account(init_if_needed, seeds = [b"publish", listing.key().as_ref()], bump, payer = owner, space = 16)]
+    pub publish_seq: Account<'info, PublishSequence>,
+    #[account(mut, constraint = listing.owner == owner.key())]
+    pub owner: Signer<'info>,
+    #[account(constraint = update_url.ready @ ErrorCode::URLNotReady)]
+    pub update_url: Account<'info, CatalogUrl>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveListing<'info> {
+    #[account(mut, constraint = catalog.catalog_id == listing.catalog)]
+    pub catalog: Account<'info, CatalogInstance>,
     #[account(mut, close = fee_recipient)]
     pub listing: Account<'info, CatalogEntry>,
+    /// CHECK: ok
+    #[account(mut, address = listing.listing_url)]
+    pub listing_url: UncheckedAccount<'info>,
+    /// CHECK: ok
+    #[account(mut, address = listing.label_url)]
+    pub label_url: UncheckedAccount<'info>,
+    /// CHECK: ok
+    #[account(mut, address = listing.detail_url)]
+    pub detail_url: UncheckedAccount<'info>,
     #[account(mut)]
     pub fee_recipient: Signer<'info>,
     #[account(constraint = catalog.manager == auth_user.key() || listing.owner == auth_user.key())]
     pub auth_user: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct CloseCatalog<'info> {
+    #[account(mut, seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    #[account(mut, seeds = [b"catalog", catalog.catalog_id.to_be_bytes().as_ref()], bump, close = fee_recipient,
+        constraint = catalog.manager == manager.key(), constraint = catalog.listing_count == 0 @ ErrorCode::CatalogNotEmpty)]
+    pub catalog: Account<'info, CatalogInstance>,
+    #[account(mut, seeds = [b"catalog_name", shake128_hash(catalog.name.as_bytes()).to_be_bytes().as_ref()], bump, close = fee_recipient)]
+    pub catalog_name: Account<'info, CatalogName>,
+    /// CHECK: ok
+    #[account(address = catalog.description_url,
+        constraint = description_url.is_writable || catalog.description_url == Pubkey::default() @ ErrorCode::InvalidParameters)]
+    pub description_url: UncheckedAccount<'info>,  // Writable, or the system program (read-only) for migrated catalogs without a description URL
+    #[account(init, seeds = [b"catalog_closed", catalog.catalog_id.to_be_bytes().as_ref()], bump, payer = manager, space = 32)]
+    pub catalog_closed: Account<'info, CatalogClosed>,
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    /// CHECK: ok
+    #[account(mut)]
+    pub fee_recipient: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 #[instruction(inp_url_expand_mode: u8, inp_url_hash: u128, inp_url_length: u32)]
 pub struct CreateURL<'info> {
-    #[account(init, seeds = [inp_url_expand_mode.to_be_bytes().as_ref(), inp_url_hash.to_be_bytes().as_ref()], bump, payer = admin, space = 13 + inp_url_length as usize)]
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    #[account(init, seeds = [inp_url_expand_mode.to_be_bytes().as_ref(), inp_url_hash.to_be_bytes().as_ref()], bump, payer = admin, space = CatalogUrl::space(inp_url_expand_mode, inp_url_length))]
     pub url_entry: Account<'info, CatalogUrl>,
     #[account(mut)]
     pub admin: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(inp_url_hash: u128)]
+pub struct CloseURL<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    /// CHECK: ok
+    pub auth_data: UncheckedAccount<'info>,     // Root authority or RoleGrant account (checked by check_role)
+    pub auth_user: Signer<'info>,
+    #[account(mut, seeds = [url_entry.url_expand_mode.to_be_bytes().as_ref(), inp_url_hash.to_be_bytes().as_ref()], bump, close = fee_recipient,
+        constraint = url_entry.version == URL_VERSION && url_entry.ref_count == 0 @ ErrorCode::URLInUse)]
+    pub url_entry: Account<'info, CatalogUrl>,
+    /// CHECK: ok
+    #[account(mut)]
+    pub fee_recipient: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(inp_url_hash: u128)]
+pub struct AbandonURL<'info> {
+    #[account(mut, seeds = [url_entry.url_expand_mode.to_be_bytes().as_ref(), inp_url_hash.to_be_bytes().as_ref()], bump, close = writer,
+        constraint = url_entry.version == URL_VERSION && ! url_entry.ready @ ErrorCode::URLAlreadyFinalized)]
+    pub url_entry: Account<'info, CatalogUrl>,
+    pub user: Signer<'info>,
+    /// CHECK: ok
+    #[account(mut, address = url_entry.writer)]
+    pub writer: AccountInfo<'info>,         // Receives the rent of the abandoned allocation
+}
+
+#[derive(Accounts)]
+#[instruction(inp_url_expand_mode: u8, inp_url_hash: u128)]
+pub struct MigrateURL<'info> {
+    /// CHECK: ok
+    #[account(mut, seeds = [inp_url_expand_mode.to_be_bytes().as_ref(), inp_url_hash.to_be_bytes().as_ref()], bump)]
+    pub url_entry: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(inp_url_hash: u128)]
+pub struct BackfillURL<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    #[account(constraint = program.programdata_address().unwrap() == Some(program_data.key()))]
+    pub program: Program<'info, Catalog>,
+    #[account(constraint = is_program_owner(&program_data, &root_data, &program_admin.key()))]
+    pub program_data: Account<'info, ProgramData>,
+    pub program_admin: Signer<'info>,
+    #[account(mut, seeds = [url_entry.url_expand_mode.to_be_bytes().as_ref(), inp_url_hash.to_be_bytes().as_ref()], bump,
+        constraint = url_entry.version == URL_VERSION_UNTRACKED)]
+    pub url_entry: Account<'info, CatalogUrl>,
+}
+
+#[derive(Accounts)]
+#[instruction(inp_url_hash: u128)]
+pub struct WriteURL<'info> {
+    #[account(seeds = [program_id.as_ref()], bump)]
+    pub root_data: Account<'info, RootData>,
+    #[account(mut, seeds = [url_entry.url_expand_mode.to_be_bytes().as_ref(), inp_url_hash.to_be_bytes().as_ref()], bump,
+        constraint = ! url_entry.ready @ ErrorCode::URLAlreadyFinalized)]
+    pub url_entry: Account<'info, CatalogUrl>,
+    #[account(constraint = url_entry.writer == writer.key())]
+    pub writer: Signer<'info>,
+}
+
 #[account]
 #[derive(Default)]
 pub struct RootData {
     pub catalog_count: u64,
     pub root_authority: Pubkey,
+    pub rbac_capacity: u32,
+    pub admin_threshold: u32,   // NetworkAdmin approvals required for grant / revoke (0 or 1 = single signer)
+    pub super_admin: Pubkey,    // Program owner when there is no upgrade authority
+    pub pending_super_admin: Pubkey,
+    pub role_admin: [u32; 16],  // Admin role for each role (index), or ROLE_ADMIN_OWNER
+    pub paused: u8,             // Network-wide PAUSE_* flags
+    pub rbac_mode: u8,          // RBACMode
+    pub rbac_version: u8,       // RBAC_VERSION of the root authority slab elements
+    pub pending_authority: Pubkey,  // RBAC account being filled by copy_rbac_page, default key = no copy in progress
+    pub pending_capacity: u32,
+    pub copy_cursor: u32,       // Next map node to copy
+    pub copy_count: u32,        // Grants copied so far
+    pub copy_close: bool,       // Close the current RBAC account when the copy completes
+    pub rbac_migrated: u32,     // RoleGrant accounts created by migrate_role_account
 }
-// Size: 8 + 8 + 32 = 48
+// Size: 8 + 8 + 32 + 4 + 4 + 32 + 32 + (4 * 16) + 1 + 1 + 1 + 32 + 4 + 4 + 4 + 1 + 4 = 236
 
 impl RootData {
     pub fn add_catalog(&mut self) -> anchor_lang::Result<()> {
@@ -648,6 +3370,44 @@ impl RootData {
         Ok(())
     }
 
+    pub fn remove_catalog(&mut self) -> anchor_lang::Result<()> {
+        self.catalog_count = self.catalog_count.checked_sub(1).ok_or(error!(ErrorCode::Overflow))?;
+        Ok(())
+    }
+
+    pub fn rbac_mode(&self) -> anchor_lang::Result<RBACMode> {
+        RBACMode::try_from_primitive(self.rbac_mode).map_err(|_| error!(ErrorCode::InternalError))
+    }
+
+    pub fn require_rbac_mode(&self, mode: RBACMode) -> anchor_lang::Result<()> {
+        if self.rbac_mode()? != mode {
+            msg!("Not available in the current RBAC mode");
+            return Err(ErrorCode::InvalidRBACMode.into());
+        }
+        if mode == RBACMode::Slab {
+            self.require_rbac_version()?;
+        }
+        Ok(())
+    }
+
+    // Slab grants cannot be read until the original layout is converted with migrate_rbac
+    pub fn require_rbac_version(&self) -> anchor_lang::Result<()> {
+        if self.rbac_version != RBAC_VERSION {
+            msg!("RBAC account must be converted with migrate_rbac");
+            return Err(ErrorCode::InvalidRBACMode.into());
+        }
+        Ok(())
+    }
+
+    pub fn role_admin(&self, role: Role) -> Option<Role> {
+        Role::try_from_primitive(self.role_admin[role as usize]).ok()
+    }
+
+    // Once the threshold is above 1 the program owner, and admins of roles managed by NetworkAdmin or the owner, need a proposal
+    pub fn requires_multisig(&self, admin_role: Option<Role>, program_owner: bool) -> bool {
+        self.admin_threshold > 1 && (program_owner || admin_role.map_or(true, |r| r == Role::NetworkAdmin))
+    }
+
     pub fn root_authority(&self) -> Pubkey {
         self.root_authority
     }
@@ -655,17 +3415,98 @@ impl RootData {
     pub fn set_root_authority(&mut self, new_authority: Pubkey) {
         self.root_authority = new_authority
     }
+
+    pub fn start_rbac_copy(&mut self, new_authority: Pubkey, capacity: u32, close: bool) {
+        self.pending_authority = new_authority;
+        self.pending_capacity = capacity;
+        self.copy_cursor = 0;
+        self.copy_count = 0;
+        self.copy_close = close;
+    }
+
+    // Grants cannot change while they are being copied into a new RBAC account
+    pub fn require_rbac_idle(&self) -> anchor_lang::Result<()> {
+        if self.pending_authority != Pubkey::default() {
+            msg!("RBAC copy in progress");
+            return Err(ErrorCode::InvalidRBACMode.into());
+        }
+        Ok(())
+    }
 }
 
 #[account]
 #[derive(Default)]
 pub struct CatalogInstance {
     pub catalog_id: u64,
-    pub catalog_counter: u64,
-    pub signer: Pubkey, // Signer for creating and updating listings
+    pub catalog_counter: u64, // Total listings created, used for listing_idx
+    pub listing_count: u64, // Live listings
+    pub signers: [CatalogSigner; 4], // Signers for creating and updating listings
     pub manager: Pubkey, // Signer for removing
+    pub paused: u8, // Catalog PAUSE_* flags
+    pub name: String, // Unique name, resolved to the id by the CatalogName account
+    pub description_url: Pubkey, // CatalogUrl
+    pub schema_version: u32, // Listing schema used by the catalog
+    pub created_ts: i64,
+    pub fee_mint: Pubkey,
+    pub fee_vault: Pubkey, // Default key = no fee policy
+    pub fee_min: u64, // Not applied to exempt owners or listing updates
+    pub fee_max: u64, // 0 = no maximum
+    pub fee_exempt: [Pubkey; 4], // Owners exempt from the minimum fee
+    pub version: u8, // CATALOG_VERSION
+}
+// Space = 8 + 8 + 8 + 8 + (48 * 4) + 32 + 1 + (4 + 64) + 32 + 4 + 8 + 32 + 32 + 8 + 8 + (32 * 4) + 1 = 578
+
+impl CatalogInstance {
+    pub fn valid_signer(&self, key: &Pubkey, now: i64) -> bool {
+        self.signers.iter().any(|s| s.key == *key && s.active(now))
+    }
+
+    pub fn check_fee(&self, owner: &Pubkey, fee_source: &AccountInfo, fee_account: &Pubkey, fee_tokens: u64, apply_min: bool) -> anchor_lang::Result<()> {
+        if self.fee_vault == Pubkey::default() {
+            return Ok(());
+        }
+        if fee_tokens > 0 {
+            if *fee_account != self.fee_vault {
+                msg!("Fee account is not the catalog fee vault");
+                return Err(ErrorCode::InvalidFee.into());
+            }
+            require!(*fee_source.owner == token::ID, ErrorCode::InvalidFee);
+            let source_data: &[u8] = &fee_source.try_borrow_data()?;
+            let source = TokenAccount::try_deserialize(&mut &source_data[..]).map_err(|_| error!(ErrorCode::InvalidFee))?;
+            if source.mint != self.fee_mint {
+                msg!("Fee source is not the catalog fee mint");
+                return Err(ErrorCode::InvalidFee.into());
+            }
+        }
+        if self.fee_max != 0 && fee_tokens > self.fee_max {
+            msg!("Fee exceeds maximum");
+            return Err(ErrorCode::InvalidFee.into());
+        }
+        let exempt = *owner != Pubkey::default() && self.fee_exempt.contains(owner);
+        if apply_min && ! exempt && fee_tokens < self.fee_min {
+            msg!("Fee below minimum");
+            return Err(ErrorCode::InvalidFee.into());
+        }
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct CatalogName {
+    pub catalog_id: u64,
+    pub catalog: Pubkey,
+}
+// Space = 8 + 8 + 32 = 48
+
+#[account]
+#[derive(Default)]
+pub struct CatalogClosed {     // Created by close_catalog, prevents the catalog id from being reused
+    pub catalog_id: u64,
+    pub catalog_counter: u64,   // Listing index high-water mark
+    pub closed_ts: i64,
 }
-// Space = 8 + 8 + 8 + 32 + 32 = 88
+// Space = 8 + 8 + 8 + 8 = 32
 
 #[account]
 #[derive(Default)]
@@ -687,13 +3528,95 @@ pub struct CatalogEntry {
 }
 // Space = 8 + 16 + 8 + 16 + (16 * 3) + 1 + 4 + 4 + 8 + 8 + 8 + (32 * 4) = 257
 
+#[account]
+#[derive(Default)]
+pub struct PublishSequence {    // Sequence for publish_update events, separate from the listing update_count used by signed updates
+    pub publish_count: u64,
+}
+// Space = 8 + 8 = 16
+
 #[account]
 #[derive(Default)]
 pub struct CatalogUrl {
     pub url_expand_mode: u8,
     pub url: String,
+    pub version: u8,            // URL_VERSION, or URL_VERSION_UNTRACKED (fields below were added after the original layout)
+    pub ready: bool,            // false while chunks are still being written
+    pub ref_count: u64,         // number of listing fields referencing this URL
+    pub writer: Pubkey,         // signer allowed to write chunks
+    pub allocated_ts: i64,      // allocation time, unfinalized URLs can be abandoned by anyone after URL_ALLOCATION_TIMEOUT
+    pub url_data: Vec<u8>,      // binary content id for IPFS / Arweave modes, or pending chunked data
+}
+// Space = 8 + 1 + (4 + len) + 1 + 1 + 8 + 32 + 8 + (4 + data len) = 67 + (len) + (data len)
+
+impl CatalogUrl {
+    pub fn space(url_expand_mode: u8, url_length: u32) -> usize {
+        match URLExpandMode::try_from_primitive(url_expand_mode) {
+            Ok(mode) if mode.is_binary() => 67 + utils::content_id_length(mode, url_length as usize),
+            _ => 67 + url_length as usize,
+        }
+    }
+
+    // Original layout: discriminator, url_expand_mode, url (space = 13 + len)
+    pub fn is_legacy(data: &[u8]) -> bool {
+        if data.len() < 13 || data[..8] != CatalogUrl::discriminator() {
+            return false;
+        }
+        let url_len = u32::from_le_bytes(data[9..13].try_into().unwrap()) as usize;
+        data.len() == 13 + url_len
+    }
+
+    pub fn expand_mode(&self) -> anchor_lang::Result<URLExpandMode> {
+        URLExpandMode::try_from_primitive(self.url_expand_mode).map_err(|_| error!(ErrorCode::InvalidURLExpandMode))
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct RoleGrant {
+    pub role: u32,
+    pub user: Pubkey,
+    pub catalog: Option<u64>,   // Some = only applies to this catalog
+    pub valid_from: i64,        // 0 = valid immediately
+    pub expires: i64,           // 0 = never expires
+    pub payer: Pubkey,          // Receives the rent when an expired grant is pruned
+}
+// Space = 8 + 4 + 32 + 9 + 8 + 8 + 32 = 101
+
+impl RoleGrant {
+    pub fn active(&self, now: i64) -> bool {
+        (self.valid_from == 0 || now >= self.valid_from) && ! self.expired(now)
+    }
+
+    pub fn expired(&self, now: i64) -> bool {
+        self.expires != 0 && now >= self.expires
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct AdminProposal {
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub action: u8,             // ProposalAction
+    pub role: u32,
+    pub user: Pubkey,
+    pub catalog: Option<u64>,
+    pub valid_from: i64,
+    pub expires: i64,
+    pub deadline: i64,          // approvals expire after this time
+    pub threshold: u32,         // new admin threshold for ProposalAction::SetThreshold, new admin role for ProposalAction::SetRoleAdmin
+    pub approvals: Vec<Pubkey>, // max MAX_APPROVALS
+}
+// Space = 8 + 8 + 32 + 1 + 4 + 32 + 9 + 8 + 8 + 8 + 4 + (4 + (32 * 10)) = 446
+
+impl AdminProposal {
+    pub fn admin_role(&self, root_data: &RootData) -> anchor_lang::Result<Option<Role>> {
+        let action = ProposalAction::try_from_primitive(self.action).map_err(|_| error!(ErrorCode::InvalidParameters))?;
+        let role = Role::try_from_primitive(self.role).map_err(|_| error!(ErrorCode::InvalidParameters))?;
+        Ok(proposal_admin_role(root_data, action, role))
+    }
 }
-// Space = 8 + 1 + (len) + 4 = 13 + (len)
 
 #[account]
 #[derive(Default)]
@@ -719,6 +3642,142 @@ pub struct RemoveListingEvent {
     pub listing_idx: u64,
 }
 
+#[event]
+pub struct PublishUpdateEvent {
+    pub owner: Pubkey,
+    pub catalog: u64,
+    pub listing: Pubkey,
+    pub listing_idx: u64,
+    pub update_url: Pubkey,
+    pub update_seq: u64,        // PublishSequence count
+    pub update_ts: i64,
+}
+
+#[event]
+pub struct CloseURLEvent {
+    pub user: Pubkey,
+    pub url_entry: Pubkey,
+    pub url_expand_mode: u8,
+    pub url_hash: u128,
+}
+
+#[event]
+pub struct RoleGrantedEvent {
+    pub admin: Pubkey,
+    pub user: Pubkey,
+    pub role: u32,
+    pub catalog: Option<u64>,
+    pub program_owner: bool,    // true if the admin acted as program owner instead of holding the admin role
+    pub slot: u32,              // UserRBAC slot, or ROLE_GRANT_SLOT
+    pub valid_from: i64,
+    pub expires: i64,
+}
+
+#[event]
+pub struct RoleRevokedEvent {
+    pub admin: Pubkey,
+    pub user: Pubkey,
+    pub role: u32,
+    pub catalog: Option<u64>,
+    pub program_owner: bool,
+    pub slot: u32,
+}
+
+#[event]
+pub struct RoleRenouncedEvent {
+    pub user: Pubkey,
+    pub role: u32,
+    pub catalog: Option<u64>,
+    pub slot: u32,
+}
+
+#[event]
+pub struct RoleUnchangedEvent {
+    pub admin: Pubkey,
+    pub user: Pubkey,
+    pub role: u32,
+    pub catalog: Option<u64>,
+    pub program_owner: bool,
+    pub grant: bool,            // true: role was already active, false: role to revoke was not found
+}
+
+#[repr(u8)]
+#[derive(PartialEq, Debug, Eq, Copy, Clone)]
+pub enum ProposalStatus {
+    Proposed,               // 0
+    Approved,               // 1
+    Executed,               // 2
+    Cancelled,              // 3
+}
+
+#[event]
+pub struct ProposalEvent {
+    pub proposal: Pubkey,
+    pub proposal_id: u64,
+    pub admin: Pubkey,
+    pub status: u8,             // ProposalStatus
+    pub approvals: u32,
+}
+
+#[event]
+pub struct CloseCatalogEvent {
+    pub user: Pubkey,
+    pub catalog: u64,
+    pub listing_total: u64,     // Listings created over the lifetime of the catalog
+}
+
+#[event]
+pub struct CatalogUpdatedEvent {
+    pub user: Pubkey,
+    pub catalog: u64,
+    pub prev_signers: [CatalogSigner; 4],
+    pub signers: [CatalogSigner; 4],
+    pub prev_manager: Pubkey,
+    pub manager: Pubkey,
+}
+
+#[event]
+pub struct FeePolicyUpdatedEvent {
+    pub user: Pubkey,
+    pub catalog: u64,
+    pub fee_mint: Pubkey,
+    pub fee_vault: Pubkey,
+    pub fee_min: u64,
+    pub fee_max: u64,
+    pub fee_exempt: Vec<Pubkey>,
+}
+
+#[event]
+pub struct PausedEvent {
+    pub user: Pubkey,
+    pub catalog: Option<u64>,   // None = network-wide
+    pub flags: u8,              // Flags set by this instruction
+    pub paused: u8,             // Resulting flags
+}
+
+#[event]
+pub struct UnpausedEvent {
+    pub user: Pubkey,
+    pub catalog: Option<u64>,   // None = network-wide
+    pub flags: u8,              // Flags cleared by this instruction
+    pub paused: u8,             // Resulting flags
+}
+
+#[event]
+pub struct SuperAdminUpdatedEvent {
+    pub prev_admin: Pubkey,
+    pub new_admin: Pubkey,
+}
+
+#[event]
+pub struct RootAuthorityUpdatedEvent {
+    pub admin: Pubkey,
+    pub prev_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub rbac_capacity: u32,
+    pub roles_copied: Option<u32>,  // None if existing grants were not copied
+}
+
 #[error_code]
 pub enum ErrorCode {
     #[msg("Access denied")]
@@ -733,4 +3792,30 @@ pub enum ErrorCode {
     InternalError,
     #[msg("Overflow")]
     Overflow,
+    #[msg("Invalid URL account")]
+    InvalidURLAccount,
+    #[msg("URL still referenced by listings")]
+    URLInUse,
+    #[msg("Invalid URL expand mode")]
+    InvalidURLExpandMode,
+    #[msg("Invalid URL format for expand mode")]
+    InvalidURLFormat,
+    #[msg("URL data not finalized")]
+    URLNotReady,
+    #[msg("URL data already finalized")]
+    URLAlreadyFinalized,
+    #[msg("Proposal expired")]
+    ProposalExpired,
+    #[msg("Paused")]
+    Paused,
+    #[msg("Not available in the current RBAC mode")]
+    InvalidRBACMode,
+    #[msg("Catalog still has listings")]
+    CatalogNotEmpty,
+    #[msg("Fee does not match catalog fee policy")]
+    InvalidFee,
+    #[msg("Listing update signed for a different update sequence")]
+    StaleUpdate,
+    #[msg("Catalog id was closed")]
+    CatalogClosed,
 }
@@ -1,11 +1,13 @@
 #!/usr/bin/env python3
 
+import os
 import json
 import uuid
 import borsh
 import base64
 import krock32
 import asyncio
+import urllib.request
 from borsh import types
 from solders.keypair import Keypair
 from solders.pubkey import Pubkey
@@ -30,9 +32,10 @@ LISTING_SCHEMA = borsh.schema({
     'fee_tokens': types.u64,
 })
 
-CATALOGS = {
-    'commerce': 0,
-}
+RPC_URL = os.environ.get('SOLANA_RPC_URL', 'http://127.0.0.1:8899')
+
+# Catalog IDs by name, resolved from the on-chain CatalogName accounts
+CATALOGS = {}
 
 decoder = krock32.Decoder(strict=False, checksum=False)
 decoder.update('pgf97nnw60g60jhyc40ga14qjage1vj8qd4pwpnn4b74gygttn3s0cm6j80abk6fmmq9ctf0s19sv48m1ftynwcargf4yczgdrj5700')
@@ -57,15 +60,32 @@ def to_text_account(text_string, fill_mode=0):
     #print(str(pda[0]))
     return [int(b) for b in bytes(pda[0])]
 
-@app.route("/api/catalog/listing", methods=['POST'])
-def catalog_listing():
-    inp = request.json
-    if not isinstance(inp, dict):
-        abort(500)
-    listing_uuid = uuid.uuid4()
-    listing_data = {
+def catalog_id(name):
+    if name in CATALOGS:
+        return CATALOGS[name]
+    shake = SHAKE128.new()
+    shake.update(name.encode('utf8'))
+    seeds = [b'catalog_name', shake.read(16)]
+    pda = Pubkey.find_program_address(seeds, Pubkey.from_string(PROGRAM))
+    req = urllib.request.Request(RPC_URL, headers={'Content-Type': 'application/json'}, data=json.dumps({
+        'jsonrpc': '2.0',
+        'id': 1,
+        'method': 'getAccountInfo',
+        'params': [str(pda[0]), {'encoding': 'base64'}],
+    }).encode('utf8'))
+    with urllib.request.urlopen(req) as rsp:
+        acct = json.load(rsp)['result']['value']
+    if acct is None:
+        raise KeyError(name)
+    # CatalogName: 8 byte discriminator, catalog_id (u64), catalog (Pubkey)
+    data = base64.b64decode(acct['data'][0])
+    CATALOGS[name] = int.from_bytes(data[8:16], 'little')
+    return CATALOGS[name]
+
+def listing_params(inp, listing_uuid):
+    return {
         'uuid': listing_uuid.int,
-        'catalog': CATALOGS[inp['catalog']],
+        'catalog': catalog_id(inp['catalog']),
         'category': int(inp['category']),
         'filter_by_1': int(inp['filter_by_1']),
         'filter_by_2': int(inp['filter_by_2']),
@@ -80,16 +100,43 @@ def catalog_listing():
         'fee_account': [int(b) for b in bytes(Pubkey.from_string(FEE_ACCOUNT))],
         'fee_tokens': 0,
     }
-    serialized_bytes = borsh.serialize(LISTING_SCHEMA, listing_data)
+
+def signed_result(listing_uuid, catalog, serialized_bytes):
     res = {}
     res['result'] = 'ok'
     res['uuid'] = str(listing_uuid)
-    res['catalog'] = str(CATALOGS[inp['catalog']])
+    res['catalog'] = str(catalog_id(catalog))
     res['pubkey'] = str(KEYPAIR.pubkey())
     res['sig'] = str(KEYPAIR.sign_message(serialized_bytes))
     res['message'] = base64.b64encode(serialized_bytes).decode('utf8')
     res['fee_mint'] = FEE_MINT
     res['fee_account'] = FEE_ACCOUNT
+    return res
+
+@app.route("/api/catalog/listing", methods=['POST'])
+def catalog_listing():
+    inp = request.json
+    if not isinstance(inp, dict):
+        abort(500)
+    listing_uuid = uuid.uuid4()
+    listing_data = listing_params(inp, listing_uuid)
+    serialized_bytes = borsh.serialize(LISTING_SCHEMA, listing_data)
+    res = signed_result(listing_uuid, inp['catalog'], serialized_bytes)
     #print(res)
     return jsonify(res)
 
+# Signed updates are only valid while the listing's update_count equals 'update_seq'
+@app.route("/api/catalog/listing/update", methods=['POST'])
+def catalog_listing_update():
+    inp = request.json
+    if not isinstance(inp, dict):
+        abort(500)
+    listing_uuid = uuid.UUID(inp['uuid'])
+    update_seq = int(inp['update_seq'])
+    listing_data = listing_params(inp, listing_uuid)
+    # ListingUpdateParameters: CatalogParameters followed by update_seq (u64)
+    serialized_bytes = bytes(borsh.serialize(LISTING_SCHEMA, listing_data)) + update_seq.to_bytes(8, 'little')
+    res = signed_result(listing_uuid, inp['catalog'], serialized_bytes)
+    res['update_seq'] = str(update_seq)
+    return jsonify(res)
+